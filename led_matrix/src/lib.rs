@@ -14,18 +14,6 @@ struct Color {
 }
 
 impl Color {
-    fn new(red: u8, green: u8, blue: u8) -> Self {
-        Self { red, green, blue }
-    }
-
-    fn default() -> Self {
-        Self {
-            red: 0,
-            blue: 0,
-            green: 0,
-        }
-    }
-
     fn random() -> Self {
         use rand::Rng;
 
@@ -47,14 +35,6 @@ impl Position {
     fn new(x: f64, y: f64) -> Self {
         Self { x, y }
     }
-
-    fn default() -> Self {
-        Self { x: 0.0, y: 0.0 }
-    }
-}
-
-trait Area {
-    fn area(&self) -> f64;
 }
 
 struct Pixel {
@@ -63,12 +43,6 @@ struct Pixel {
     color: Color,
 }
 
-impl Area for Pixel {
-    fn area(&self) -> f64 {
-        self.size.powf(2.0)
-    }
-}
-
 impl Pixel {
     fn new(position: Position, color: Color, size: f64) -> Self {
         Self {
@@ -111,12 +85,6 @@ struct Constraints {
     y2: f64,
 }
 
-impl Area for Constraints {
-    fn area(&self) -> f64 {
-        (self.x2 - self.x1) * (self.y2 - self.y1)
-    }
-}
-
 trait Draw {
     fn draw(&mut self, context: &CanvasRenderingContext2d);
 }
@@ -183,7 +151,7 @@ pub fn led_matrix() -> Result<(), JsValue> {
         .dyn_into::<CanvasRenderingContext2d>()
     {
         let closure =
-            Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {}) as Box<dyn FnMut(_)>);
+            Closure::wrap(Box::new(move |_event: web_sys::MouseEvent| {}) as Box<dyn FnMut(_)>);
 
         canvas.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref())?;
         canvas.add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())?;
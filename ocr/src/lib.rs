@@ -23,62 +23,8 @@ impl PointBuilder for Point {
     }
 }
 
-struct BoundingBox {
-    max_x: f64,
-    min_x: f64,
-    max_y: f64,
-    min_y: f64,
-}
-
-trait BBBuilder {
-    fn new(x1: f64, x2: f64, y1: f64, y2: f64) -> Self;
-}
-
-impl BBBuilder for BoundingBox {
-    fn new(x1: f64, x2: f64, y1: f64, y2: f64) -> Self {
-        BoundingBox {
-            max_x: x1,
-            min_x: x2,
-            max_y: y1,
-            min_y: y2,
-        }
-    }
-}
-
-struct AspectRatio {
-    height: f64,
-    width: f64,
-    ratio: f64,
-}
-
-trait CanBox {
-    fn new(w: f64, h: f64) -> Self;
-
-    fn from_bounding_box(b: BoundingBox) -> Self;
-}
-
-impl CanBox for AspectRatio {
-    fn new(w: f64, h: f64) -> Self {
-        let ratio = w / h;
-        AspectRatio {
-            height: h,
-            width: w,
-            ratio,
-        }
-    }
-
-    fn from_bounding_box(b: BoundingBox) -> Self {
-        let height = b.max_y - b.min_y;
-        let width = b.max_x - b.min_x;
-
-        Self::new(width, height)
-    }
-}
-
 struct Character {
     points: Vec<Point>,
-    aspect_ratio: AspectRatio,
-    bounding_box: BoundingBox,
     label: char,
 }
 
@@ -86,7 +32,7 @@ fn euclidean_distance(p1: &Character, p2: &Character) -> f64 {
     let mut sum = 0.0;
 
     for Point(x1, y1) in &p1.points {
-        let Point(x2, y2) = p2.points.iter().next().unwrap();
+        let Point(x2, y2) = p2.points.first().unwrap();
         sum += ((x1 - x2).powf(2.0) + (y1 - y2).powf(2.0)).sqrt();
     }
     sum
@@ -136,12 +82,6 @@ fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
 
-fn request_animation_frame(f: &Closure<dyn FnMut()>) {
-    window()
-        .request_animation_frame(f.as_ref().unchecked_ref())
-        .expect("should register `requestAnimationFrame` OK");
-}
-
 fn document() -> web_sys::Document {
     window()
         .document()
@@ -156,8 +96,6 @@ fn body() -> web_sys::HtmlElement {
 pub fn ocr() -> Result<(), JsValue> {
     let training_data = Vec::from([
         Character {
-            bounding_box: BoundingBox::new(0.0, 200.0, 0.0, 200.0),
-            aspect_ratio: AspectRatio::new(100.0, 100.0),
             label: '1',
             points: vec![
                 Point::new(10.0, 10.0),
@@ -171,8 +109,6 @@ pub fn ocr() -> Result<(), JsValue> {
             ],
         },
         Character {
-            bounding_box: BoundingBox::new(0.0, 200.0, 0.0, 200.0),
-            aspect_ratio: AspectRatio::new(100.0, 100.0),
             label: '1',
             points: vec![
                 Point::new(10.0, 10.0),
@@ -186,8 +122,6 @@ pub fn ocr() -> Result<(), JsValue> {
             ],
         },
         Character {
-            bounding_box: BoundingBox::new(0.0, 200.0, 0.0, 200.0),
-            aspect_ratio: AspectRatio::new(100.0, 100.0),
             label: '2',
             points: vec![
                 Point::new(10.0, 10.0),
@@ -202,8 +136,6 @@ pub fn ocr() -> Result<(), JsValue> {
             ],
         },
         Character {
-            bounding_box: BoundingBox::new(0.0, 200.0, 0.0, 200.0),
-            aspect_ratio: AspectRatio::new(100.0, 100.0),
             label: '2',
             points: vec![
                 Point::new(10.0, 10.0),
@@ -225,8 +157,6 @@ pub fn ocr() -> Result<(), JsValue> {
     };
 
     let input_data = Character {
-        bounding_box: BoundingBox::new(0.0, 200.0, 0.0, 200.0),
-        aspect_ratio: AspectRatio::new(100.0, 100.0),
         label: '1',
         points: vec![
             Point::new(10.0, 10.0),
@@ -266,8 +196,6 @@ pub fn ocr() -> Result<(), JsValue> {
         .append_child(&canvas)
         .expect("Failed to append ocr")
         .set_text_content(Some("Ocr suppose to be init in here!"));
-    // let f = Rc::new(RefCell::new(None));
-    // let g = f.clone();
     if let Ok(context) = canvas
         .get_context("2d")
         .unwrap()
@@ -294,11 +222,9 @@ pub fn ocr() -> Result<(), JsValue> {
                         context.begin_path();
                         context.move_to(event.offset_x() as f64, event.offset_y() as f64);
                     }
-                    "mousemove" => {
-                        if is_drawing == true {
-                            context.line_to((event.offset_x()) as f64, (event.offset_y()) as f64);
-                            context.stroke();
-                        }
+                    "mousemove" if is_drawing => {
+                        context.line_to((event.offset_x()) as f64, (event.offset_y()) as f64);
+                        context.stroke();
                     }
                     _ => {}
                 },
@@ -309,13 +235,6 @@ pub fn ocr() -> Result<(), JsValue> {
         canvas.add_event_listener_with_callback("mouseup", closure.as_ref().unchecked_ref())?;
 
         closure.forget();
-
-        // *g.borrow_mut() = Some(Closure::new(move || {
-        //
-        //     request_animation_frame(f.borrow().as_ref().unwrap());
-        // }));
-        //
-        // request_animation_frame(g.borrow().as_ref().unwrap());
     }
     Ok(())
 }
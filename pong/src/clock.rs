@@ -0,0 +1,48 @@
+//! The fixed-step clock behind `PongGame::frame`, kept free of the browser so
+//! it can be tested natively.
+
+use pong_core::STEPS_PER_SECOND;
+use pong_protocol::{Position, Snapshot};
+
+/// Milliseconds of game time per step.
+pub const STEP_MS: f64 = 1000.0 / STEPS_PER_SECOND as f64;
+/// Longer frames are cut short, so a stalled tab catches up in a few steps.
+pub const MAX_FRAME_MS: f64 = 250.0;
+
+/// Milliseconds between the last frame and `now`, zero for the first frame
+/// and never more than `MAX_FRAME_MS`.
+pub fn frame_time(last_frame: Option<f64>, now: f64) -> f64 {
+    last_frame.map_or(0.0, |last| (now - last).clamp(0.0, MAX_FRAME_MS))
+}
+
+/// Adds `elapsed` to `accumulator` and returns how many whole steps that
+/// covers, along with the time left over for the next frame.
+pub fn advance(accumulator: f64, elapsed: f64) -> (u32, f64) {
+    let mut accumulator = accumulator + elapsed;
+    let mut steps = 0;
+    while accumulator >= STEP_MS {
+        accumulator -= STEP_MS;
+        steps += 1;
+    }
+    (steps, accumulator)
+}
+
+fn lerp(from: Position, to: Position, alpha: f64) -> Position {
+    Position::new(
+        from.x + (to.x - from.x) * alpha,
+        from.y + (to.y - from.y) * alpha,
+    )
+}
+
+/// The positions to draw `accumulator` milliseconds after the step that
+/// moved `previous` to `current`.
+pub fn blend(previous: &Snapshot, current: &Snapshot, accumulator: f64) -> Snapshot {
+    let alpha = (accumulator / STEP_MS).clamp(0.0, 1.0);
+    Snapshot {
+        ball: lerp(previous.ball, current.ball, alpha),
+        paddles: (
+            lerp(previous.paddles.0, current.paddles.0, alpha),
+            lerp(previous.paddles.1, current.paddles.1, alpha),
+        ),
+    }
+}
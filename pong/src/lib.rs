@@ -1,359 +1,201 @@
+pub mod clock;
+
+use clock::{advance, blend, frame_time, STEP_MS};
+use pong_core::{Game, Inputs, BALL_RADIUS, HEIGHT, PADDLE_HEIGHT, PADDLE_WIDTH, WIDTH};
+use pong_protocol::{
+    ClientMessage, Direction, Phase, Position, ServerMessage, Side, Snapshot, Target, VERSION,
+};
 use std::{cell::RefCell, f64::consts::PI, rc::Rc};
 use wasm_bindgen::prelude::*;
 use web_sys::{
-    CanvasRenderingContext2d, ErrorEvent, HtmlCanvasElement, KeyboardEvent, MessageEvent, WebSocket,
+    CanvasRenderingContext2d, ErrorEvent, HtmlCanvasElement, KeyboardEvent, MessageEvent,
+    UrlSearchParams, WebSocket,
 };
 
-const G_WIDTH: u32 = 500;
-const G_HEIGHT: u32 = 300;
-const PADDLE_WIDTH: f64 = 12.0;
-const PADDLE_HEIGHT: f64 = 80.0;
-const BALL_RADIUS: f64 = 6.0;
-
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
-enum Direction {
-    Up,
-    Down,
-}
-
-enum Side {
-    Left,
-    Right,
-}
-
-struct Position {
-    x: f64,
-    y: f64,
-}
-
-trait Distance {
-    fn distance_from(&self, other_position: &Position) -> f64;
-}
-
-impl Position {
-    fn new(x: f64, y: f64) -> Self {
-        Self { x, y }
-    }
-}
-
-impl Distance for Position {
-    fn distance_from(&self, other: &Position) -> f64 {
-        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
-    }
-}
-
-trait Update {
-    fn update(&mut self, new_x: f64, new_y: f64) -> &mut Self;
-}
-
-impl Update for Position {
-    fn update(&mut self, new_x: f64, new_y: f64) -> &mut Self {
-        self.x = new_x;
-        self.y = new_y;
-        self
-    }
-}
-
-struct Line {
-    p1: Position,
-    p2: Position,
-}
-
-impl Collide for Line {
-    fn collide_with_ball(&self, ball: &Ball) -> bool {
-        ball.position.distance_from(&self.p1) + ball.position.distance_from(&self.p2)
-            > self.p1.distance_from(&self.p2) - (ball.radius / 2.0)
-            && ball.position.distance_from(&self.p1) + ball.position.distance_from(&self.p2)
-                < self.p1.distance_from(&self.p2) + (ball.radius / 2.0)
+fn direction_from_key(key: &str) -> Option<Direction> {
+    match key {
+        "ArrowUp" | "j" => Some(Direction::Up),
+        "ArrowDown" | "k" => Some(Direction::Down),
+        _ => None,
     }
 }
 
-trait Collide {
-    fn collide_with_ball(&self, ball: &Ball) -> bool;
+fn draw_ball(context: &CanvasRenderingContext2d, ball: Position) {
+    context.begin_path();
+    context
+        .arc(ball.x, ball.y, BALL_RADIUS, 0.0, PI * 2.0)
+        .expect("Failed to draw arc");
+    context.fill();
+    context.close_path();
 }
 
-struct Constraints {
-    x1: f64,
-    x2: f64,
-    y1: f64,
-    y2: f64,
+fn draw_paddle(context: &CanvasRenderingContext2d, paddle: Position) {
+    context.begin_path();
+    context.rect(paddle.x, paddle.y, PADDLE_WIDTH, PADDLE_HEIGHT);
+    context.fill();
+    context.close_path();
 }
 
-trait Draw {
-    fn draw(&mut self, context: &CanvasRenderingContext2d);
+fn draw_score(context: &CanvasRenderingContext2d, value: u32, x: f64) {
+    context.set_fill_style(&JsValue::from_str("black"));
+    context.set_font("20px HackNerdFont");
+    context
+        .fill_text(&value.to_string(), x, 30.0)
+        .expect("Failed to fill score");
 }
 
-struct Ball {
-    position: Position,
-    radius: f64,
+/// Dims the field and writes `text` across the middle.
+fn draw_overlay(context: &CanvasRenderingContext2d, text: &str) {
+    context.set_fill_style(&JsValue::from_str("rgba(255, 255, 255, 0.7)"));
+    context.fill_rect(0.0, 0.0, WIDTH, HEIGHT);
+    context.set_fill_style(&JsValue::from_str("black"));
+    context.set_font("24px HackNerdFont");
+    context.set_text_align("center");
+    context
+        .fill_text(text, WIDTH / 2.0, HEIGHT / 2.0)
+        .expect("Failed to fill overlay");
+    context.set_text_align("start");
 }
 
-impl Ball {
-    fn new(x: f64, y: f64, radius: f64) -> Self {
-        Ball {
-            position: Position { x, y },
-            radius,
-        }
-    }
-    fn update(&mut self, new_x: f64, new_y: f64) -> &mut Self {
-        self.position.update(new_x, new_y);
-        self
-    }
-}
-
-impl Draw for Ball {
-    fn draw(&mut self, context: &CanvasRenderingContext2d) {
-        context.begin_path();
-        context
-            .arc(self.position.x, self.position.y, self.radius, 0.0, PI * 2.0)
-            .expect("Failed to draw arc");
-        context.fill();
-        context.close_path();
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Left => "left",
+        Side::Right => "right",
     }
 }
 
-struct Paddle {
-    position: Position,
-    width: f64,
-    height: f64,
-    side: Side,
-    collision_line: Line,
-}
-
-impl Paddle {
-    fn new(position: Position, width: f64, height: f64, side: Side) -> Self {
-        let collision_line =
-            Self::create_collision_line(&side, &position.x, &position.y, &width, &height);
-        Self {
-            position,
-            width,
-            height,
-            side,
-            collision_line,
-        }
-    }
-    fn update(&mut self, new_x: f64, new_y: f64) -> &mut Self {
-        let collision_line =
-            Self::create_collision_line(&self.side, &new_x, &new_y, &self.width, &self.height);
-        self.collision_line = collision_line;
-        self.position.update(new_x, new_y);
-        self
-    }
-    fn create_collision_line(side: &Side, x: &f64, y: &f64, width: &f64, height: &f64) -> Line {
-        match side {
-            Side::Left => Line {
-                p1: Position::new(x + width, *y),
-                p2: Position::new(x + width, y + height),
-            },
-            Side::Right => Line {
-                p1: Position::new(*x, *y),
-                p2: Position::new(*x, y + height),
-            },
-        }
-    }
-}
-
-impl Draw for Paddle {
-    fn draw(&mut self, context: &CanvasRenderingContext2d) {
-        context.begin_path();
-        context.rect(self.position.x, self.position.y, self.width, self.height);
-        context.fill();
-        context.close_path();
-    }
-}
-
-struct Score {
-    position: Position,
-    value: u32,
-}
-
-impl Draw for Score {
-    fn draw(&mut self, context: &CanvasRenderingContext2d) {
-        context.set_fill_style(&JsValue::from_str("black"));
-        context.set_font("20px HackNerdFont");
-        context
-            .fill_text(&self.value.to_string(), self.position.x, self.position.y)
-            .expect("Failed to fill score");
+/// What the overlay says in `phase`, nothing during a rally.
+fn overlay(phase: Phase) -> Option<String> {
+    match phase {
+        Phase::Lobby => Some(String::from("Waiting for an opponent")),
+        Phase::Countdown { seconds } => Some(seconds.to_string()),
+        Phase::Serve { toward } => Some(format!("Serving {}", side_name(toward))),
+        Phase::Rally => None,
+        Phase::Point { scorer } => Some(format!("Point {}", side_name(scorer))),
+        Phase::GameOver { winner } => Some(format!(
+            "{} wins! Press a key for a rematch",
+            match winner {
+                Side::Left => "Left",
+                Side::Right => "Right",
+            }
+        )),
     }
 }
 
-#[derive(Eq, Hash, PartialEq)]
-struct Player {
-    connection: Connection,
-    id: u32,
-}
-
-#[derive(Eq, Hash, PartialEq)]
-enum Connection {
-    Estabilshed,
-    Nope,
-}
-
+/// The client's view of a match. Played locally until the server hands out
+/// a side, then mirrors the server's snapshots.
 struct PongGame {
-    ball: Ball,
-    ball_direction_x: f64,
-    ball_direction_y: f64,
-    paddles: (Paddle, Paddle),
-    players: (Player, Player),
-    speed: f64,
-    scores: (Score, Score),
-    constraints: Constraints,
-    collision_lines: (Line, Line),
+    game: Game,
+    /// Local key presses since the last step.
+    inputs: Inputs,
+    /// Whether the left and right seats are taken.
+    players: (bool, bool),
+    scores: (u32, u32),
+    phase: Phase,
+    room: Option<String>,
+    side: Option<Side>,
+    /// Positions before the latest step, drawn blended into the current ones.
+    previous: Snapshot,
+    /// Milliseconds since the latest step that are not stepped yet.
+    accumulator: f64,
+    /// `performance.now()` at the last frame, unset while paused.
+    last_frame: Option<f64>,
+    paused: bool,
 }
 
 impl PongGame {
-    fn new(constraints: Constraints) -> Self {
-        let ball = Ball::new(15.0, 50.0, BALL_RADIUS);
-
-        let player_one = Player {
-            id: 1,
-            connection: Connection::Nope,
-        };
-        let player_two = Player {
-            id: 2,
-            connection: Connection::Nope,
-        };
-
-        let paddle_one = Paddle::new(
-            Position::new(constraints.x1, constraints.y1),
-            PADDLE_WIDTH,
-            PADDLE_HEIGHT,
-            Side::Left,
-        );
-        let paddle_two = Paddle::new(
-            Position::new(
-                constraints.x2 - PADDLE_WIDTH,
-                constraints.y2 - PADDLE_HEIGHT,
-            ),
-            PADDLE_WIDTH,
-            PADDLE_HEIGHT,
-            Side::Right,
-        );
-
-        let score_1 = Score {
-            value: 5,
-            position: Position::new(
-                (constraints.x1 + constraints.x2) / 2.0 - 50.0,
-                constraints.y1 + 30.0,
-            ),
-        };
-
-        let score_2 = Score {
-            value: 5,
-            position: Position::new(
-                (constraints.x1 + constraints.x2) / 2.0 + 50.0,
-                constraints.y1 + 30.0,
-            ),
-        };
-
-        let collision_line1 = Line {
-            p1: Position::new(constraints.x1, constraints.y1),
-            p2: Position::new(constraints.x1, constraints.y2),
-        };
-        let collision_line2 = Line {
-            p1: Position::new(constraints.x2, constraints.y1),
-            p2: Position::new(constraints.x2, constraints.y2),
-        };
-
+    fn new() -> Self {
+        let mut game = Game::new();
+        game.start();
         PongGame {
-            ball,
-            scores: (score_1, score_2),
-            paddles: (paddle_one, paddle_two),
-            players: (player_one, player_two),
-            speed: 6.0,
-            constraints,
-            ball_direction_x: 1.0,
-            ball_direction_y: 1.0,
-            collision_lines: (collision_line1, collision_line2),
+            scores: game.scores(),
+            phase: game.phase(),
+            previous: game.snapshot(),
+            game,
+            inputs: Inputs::default(),
+            players: (false, false),
+            room: None,
+            side: None,
+            accumulator: 0.0,
+            last_frame: None,
+            paused: false,
         }
     }
 
-    fn move_ball(&mut self) {
-        let mut new_x: f64 = 0.0;
-        let mut new_y: f64 = 0.0;
-
-        let is_0_collide = self.paddles.0.collision_line.collide_with_ball(&self.ball);
-        let is_1_collide = self.paddles.1.collision_line.collide_with_ball(&self.ball);
-        let is_left_line_collide = self.collision_lines.0.collide_with_ball(&self.ball);
-        let is_right_line_collide = self.collision_lines.1.collide_with_ball(&self.ball);
-
-        if is_0_collide && self.ball_direction_x < 0.0 {
-            self.ball_direction_x *= -1.0;
+    /// Runs as many fixed steps as the time since the last frame covers, so
+    /// the game plays at the same speed whatever the display's refresh rate.
+    fn frame(&mut self, now: f64) {
+        if self.paused {
+            return;
         }
-        if is_left_line_collide && self.ball_direction_x < 0.0 {
-            self.scores.0.value -= 1;
+        let elapsed = frame_time(self.last_frame.replace(now), now);
+        if self.side.is_some() {
+            // The server steps the game, blend towards its latest snapshot.
+            self.accumulator = (self.accumulator + elapsed).min(STEP_MS);
+            return;
         }
-        if is_1_collide && self.ball_direction_x > 0.0 {
-            self.ball_direction_x *= -1.0;
-        }
-        if is_right_line_collide && self.ball_direction_x > 0.0 {
-            self.scores.1.value -= 1;
+        let (steps, accumulator) = advance(self.accumulator, elapsed);
+        for _ in 0..steps {
+            self.previous = self.game.snapshot();
+            self.game.step(std::mem::take(&mut self.inputs));
+            self.scores = self.game.scores();
+            self.phase = self.game.phase();
         }
+        self.accumulator = accumulator;
+    }
 
-        if self.ball.position.y <= self.constraints.y1 && self.ball_direction_y < 0.0
-            || self.ball.position.y >= self.constraints.y2 && self.ball_direction_y > 0.0
-        {
-            self.ball_direction_y *= -1.0;
-        }
+    fn receive(&mut self, snapshot: &Snapshot) {
+        self.previous = self.game.snapshot();
+        self.game.apply(snapshot);
+        self.accumulator = 0.0;
+    }
 
-        new_x += self.speed * self.ball_direction_x + self.ball.position.x;
-        new_y += self.speed * self.ball_direction_y + self.ball.position.y;
-        self.ball.update(new_x, new_y);
+    /// Stops the clock while the tab is hidden, so the ball carries on from
+    /// where it was rather than jumping ahead when the tab comes back.
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.last_frame = None;
     }
 
-    fn move_paddle(&mut self, direction: Direction, paddle: usize) {
-        match paddle {
-            0 => {
-                let new_x = self.paddles.0.position.x;
-                match direction {
-                    Direction::Down => {
-                        let new_y = self.paddles.0.position.y + 5.0 + self.speed * 5.0;
-                        if self.constraints.y2 - PADDLE_HEIGHT >= new_y {
-                            self.paddles.0.update(new_x, new_y);
-                        }
-                    }
-                    Direction::Up => {
-                        let new_y = self.paddles.0.position.y + 5.0 - self.speed * 5.0;
-                        if self.constraints.y1 <= new_y {
-                            self.paddles.0.update(new_x, new_y);
-                        }
-                    }
-                }
-            }
-            1 => {
-                let new_x = self.paddles.1.position.x;
-                match direction {
-                    Direction::Down => {
-                        let new_y = self.paddles.1.position.y + 5.0 + self.speed * 5.0;
-                        if self.constraints.y2 - PADDLE_HEIGHT >= new_y {
-                            self.paddles.1.update(new_x, new_y);
-                        }
-                    }
-                    Direction::Up => {
-                        let new_y = self.paddles.1.position.y + 5.0 - self.speed * 5.0;
-                        if self.constraints.y1 <= new_y {
-                            self.paddles.1.update(new_x, new_y);
-                        }
-                    }
-                }
+    fn status(&self) -> String {
+        let (Some(room), Some(side)) = (&self.room, self.side) else {
+            return String::from("Connecting...");
+        };
+        let waiting = !(self.players.0 && self.players.1);
+        format!(
+            "Room {room} - you are {} - {}",
+            match side {
+                Side::Left => "left ('j', 'k')",
+                Side::Right => "right ('Up', 'Down')",
+            },
+            if waiting {
+                "waiting for opponent"
+            } else {
+                "playing"
             }
-            _ => {}
-        }
+        )
     }
-}
 
-impl Draw for PongGame {
-    fn draw(&mut self, context: &CanvasRenderingContext2d) {
-        self.ball.draw(context);
-        self.paddles.0.draw(context);
-        self.paddles.1.draw(context);
-        self.scores.0.draw(context);
-        self.scores.1.draw(context);
+    fn draw(&self, context: &CanvasRenderingContext2d) {
+        let shown = blend(&self.previous, &self.game.snapshot(), self.accumulator);
+        draw_ball(context, shown.ball);
+        draw_paddle(context, shown.paddles.0);
+        draw_paddle(context, shown.paddles.1);
+        draw_score(context, self.scores.0, WIDTH / 2.0 - 50.0);
+        draw_score(context, self.scores.1, WIDTH / 2.0 + 50.0);
+
+        // An online match pauses while a seat is empty, whatever its phase.
+        let waiting = self.side.is_some() && !(self.players.0 && self.players.1);
+        let phase = if waiting { Phase::Lobby } else { self.phase };
+        if let Some(text) = overlay(phase) {
+            draw_overlay(context, &text);
+        }
     }
 }
 
@@ -377,52 +219,129 @@ fn body() -> web_sys::HtmlElement {
     document().body().expect("document should have a body")
 }
 
-fn on_message(event: MessageEvent) {
-    if let Ok(text) = event.data().dyn_into::<web_sys::js_sys::JsString>() {
-        let message = text.as_string().unwrap();
-        log(format!("Received: {}", message).as_str());
+fn set_status(text: &str) {
+    if let Some(status) = document().get_element_by_id("pong-status") {
+        status.set_text_content(Some(text));
     }
 }
 
-fn on_error(event: ErrorEvent) {
-    log(format!("WebSocket error: {:?}", event).as_str());
+fn socket_url() -> Result<String, JsValue> {
+    let location = window().location();
+    let scheme = if location.protocol()? == "https:" {
+        "wss:"
+    } else {
+        "ws:"
+    };
+    Ok(format!("{scheme}//{}/ws", location.host()?))
+}
+
+fn session_storage() -> Option<web_sys::Storage> {
+    window().session_storage().ok().flatten()
+}
+
+fn stored_session() -> Option<(String, String)> {
+    let storage = session_storage()?;
+    let room = storage.get_item("pong-room").ok()??;
+    let token = storage.get_item("pong-token").ok()??;
+    Some((room, token))
+}
+
+fn store_session(room: &str, token: &str) {
+    if let Some(storage) = session_storage() {
+        let _ = storage.set_item("pong-room", room);
+        let _ = storage.set_item("pong-token", token);
+    }
+}
+
+fn clear_session() {
+    if let Some(storage) = session_storage() {
+        let _ = storage.remove_item("pong-room");
+        let _ = storage.remove_item("pong-token");
+    }
+}
+
+fn requested_room() -> Option<String> {
+    let search = window().location().search().ok()?;
+    UrlSearchParams::new_with_str(&search).ok()?.get("room")
+}
+
+fn join_message(room: Option<&str>) -> ClientMessage {
+    let target = match room {
+        Some("new") => Target::Create,
+        Some(room) => Target::Room(room.to_string()),
+        None => Target::Queue,
+    };
+    ClientMessage::Join {
+        version: VERSION,
+        target,
+    }
 }
 
-fn on_open() {
-    log(format!("WebSocket connected").as_str());
+fn send(socket: &WebSocket, message: &ClientMessage) {
+    if let Err(err) = socket.send_with_str(&message.encode()) {
+        log(&format!("Failed to send message: {:?}", err));
+    }
+}
+
+fn on_message(game: &mut PongGame, socket: &WebSocket, rejoining: &mut bool, text: &str) {
+    let message = match ServerMessage::decode(text) {
+        Ok(message) => message,
+        Err(err) => {
+            log(&format!("Unexpected message {text}: {err}"));
+            return;
+        }
+    };
+
+    match message {
+        ServerMessage::Welcome {
+            room, side, token, ..
+        } => {
+            *rejoining = false;
+            store_session(&room, &token);
+            game.room = Some(room);
+            game.side = Some(side);
+        }
+        ServerMessage::Players { left, right } => game.players = (left, right),
+        ServerMessage::State(snapshot) => game.receive(&snapshot),
+        ServerMessage::Score { left, right } => game.scores = (left, right),
+        ServerMessage::Phase(phase) => game.phase = phase,
+        ServerMessage::Pong { .. } => {}
+        ServerMessage::Error { message } => {
+            log(&format!("Pong server: {message}"));
+            if *rejoining {
+                *rejoining = false;
+                clear_session();
+                send(socket, &join_message(requested_room().as_deref()));
+                return;
+            }
+            set_status(&message);
+            return;
+        }
+    }
+    set_status(&game.status());
+}
+
+fn on_error(event: ErrorEvent) {
+    log(format!("WebSocket error: {:?}", event).as_str());
 }
 
 fn on_close() {
-    log(format!("WebSocket disconnected").as_str());
+    log("WebSocket disconnected");
+    set_status("Disconnected from the server, reload to play again");
 }
 
 #[wasm_bindgen(start)]
 pub fn pong_game() -> Result<(), JsValue> {
-    // let on_message_callback = Closure::wrap(Box::new(on_message) as Box<dyn FnMut(MessageEvent)>);
-    // socket.set_onmessage(Some(on_message_callback.as_ref().unchecked_ref()));
-    //
-    // let on_error_callback = Closure::wrap(Box::new(on_error) as Box<dyn FnMut(ErrorEvent)>);
-    // socket.set_onerror(Some(on_error_callback.as_ref().unchecked_ref()));
-    //
-    // let on_open_callback = Closure::wrap(Box::new(on_open) as Box<dyn FnMut()>);
-    // socket.set_onopen(Some(on_open_callback.as_ref().unchecked_ref()));
-    //
-    // let on_close_callback = Closure::wrap(Box::new(on_close) as Box<dyn FnMut()>);
-    // socket.set_onclose(Some(on_close_callback.as_ref().unchecked_ref()));
-
-    let constraints = Constraints {
-        x1: 0.0,
-        x2: G_WIDTH as f64,
-        y1: 0.0,
-        y2: G_HEIGHT as f64,
-    };
-
-    let game = Rc::new(RefCell::new(PongGame::new(constraints)));
-    let socket = Rc::new(RefCell::new(WebSocket::new("ws://0.0.0.0:8080/ws")?));
+    let game = Rc::new(RefCell::new(PongGame::new()));
+    let socket = Rc::new(WebSocket::new(&socket_url()?)?);
+    let rejoining = Rc::new(RefCell::new(false));
 
     let game_keydown = Rc::clone(&game);
     let game_socket = Rc::clone(&game);
     let socket_key_control = Rc::clone(&socket);
+    let socket_open = Rc::clone(&socket);
+    let socket_messages = Rc::clone(&socket);
+    let rejoining_open = Rc::clone(&rejoining);
 
     let canvas: HtmlCanvasElement = document()
         .create_element("canvas")
@@ -430,8 +349,8 @@ pub fn pong_game() -> Result<(), JsValue> {
         .dyn_into()
         .expect("Failed to convert to HtmlCanvasElement");
 
-    canvas.set_width(G_WIDTH);
-    canvas.set_height(G_HEIGHT);
+    canvas.set_width(WIDTH as u32);
+    canvas.set_height(HEIGHT as u32);
 
     body()
         .owner_document()
@@ -442,49 +361,78 @@ pub fn pong_game() -> Result<(), JsValue> {
         .expect("Failed to append game")
         .set_text_content(Some("Pong suppsoe to be init in here!"));
 
-    let closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+    let keydown_callback = Closure::<dyn FnMut(_)>::new(move |event: KeyboardEvent| {
+        let key = event.key();
         let mut game = game_keydown.borrow_mut();
-        match event.key().as_str() {
-            "ArrowUp" => game.move_paddle(Direction::Up, 1),
-            "ArrowDown" => game.move_paddle(Direction::Down, 1),
-            "j" => game.move_paddle(Direction::Up, 0),
-            "k" => game.move_paddle(Direction::Down, 0),
-            _ => (),
+        if game.side.is_some() {
+            if let Some(direction) = direction_from_key(&key) {
+                send(&socket_key_control, &ClientMessage::Input { direction });
+            }
+            return;
         }
-    }) as Box<dyn FnMut(_)>);
-
-    let paddle_socket_callback = Closure::<dyn FnMut(_)>::new(move |event: KeyboardEvent| {
-        log("paddle_socket_callback");
-        let socket = socket_key_control.borrow_mut();
-        if let Err(err) = socket.send_with_str(event.key().as_str()) {
-            println!("Failed to send message: {:?}", err);
+        let side = match key.as_str() {
+            "ArrowUp" | "ArrowDown" => Side::Right,
+            _ => Side::Left,
+        };
+        if let Some(direction) = direction_from_key(&key) {
+            game.inputs.press(side, direction);
         }
     });
 
-    body().add_event_listener_with_callback(
-        "keydown",
-        paddle_socket_callback.as_ref().unchecked_ref(),
-    )?;
+    body()
+        .add_event_listener_with_callback("keydown", keydown_callback.as_ref().unchecked_ref())?;
+
+    let onopen_callback = Closure::<dyn FnMut()>::new(move || {
+        let room = requested_room();
+        let message = match stored_session() {
+            Some((stored, token)) if room.is_none() || room.as_deref() == Some(&stored) => {
+                *rejoining_open.borrow_mut() = true;
+                ClientMessage::Join {
+                    version: VERSION,
+                    target: Target::Rejoin {
+                        room: stored,
+                        token,
+                    },
+                }
+            }
+            _ => join_message(room.as_deref()),
+        };
+        send(&socket_open, &message);
+    });
 
     let onmessage_callback = Closure::<dyn FnMut(_)>::new(move |e: MessageEvent| {
-        log(&e.data().as_string().unwrap());
-        let message = &e.data().as_string().unwrap();
+        let Some(text) = e.data().as_string() else {
+            log("Unexpected binary message from the server");
+            return;
+        };
         let mut game = game_socket.borrow_mut();
-        match message.as_str() {
-            "ArrowUp" => game.move_paddle(Direction::Up, 1),
-            "ArrowDown" => game.move_paddle(Direction::Down, 1),
-            "j" => game.move_paddle(Direction::Up, 0),
-            "k" => game.move_paddle(Direction::Down, 0),
-            _ => (),
-        }
+        let mut rejoining = rejoining.borrow_mut();
+        on_message(&mut game, &socket_messages, &mut rejoining, &text);
     });
 
-    let socket_messages = socket.borrow_mut();
-    socket_messages.set_onmessage(Some(onmessage_callback.as_ref().unchecked_ref()));
+    let onerror_callback = Closure::<dyn FnMut(_)>::new(on_error);
+    let onclose_callback = Closure::<dyn FnMut()>::new(on_close);
 
+    socket.set_onopen(Some(onopen_callback.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(onmessage_callback.as_ref().unchecked_ref()));
+    socket.set_onerror(Some(onerror_callback.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(onclose_callback.as_ref().unchecked_ref()));
+
+    onopen_callback.forget();
     onmessage_callback.forget();
-    paddle_socket_callback.forget();
-    closure.forget();
+    onerror_callback.forget();
+    onclose_callback.forget();
+    keydown_callback.forget();
+
+    let game_visibility = Rc::clone(&game);
+    let visibility_callback = Closure::<dyn FnMut()>::new(move || {
+        game_visibility.borrow_mut().set_paused(document().hidden());
+    });
+    document().add_event_listener_with_callback(
+        "visibilitychange",
+        visibility_callback.as_ref().unchecked_ref(),
+    )?;
+    visibility_callback.forget();
 
     let game_animation = Rc::clone(&game);
     let f = Rc::new(RefCell::new(None));
@@ -498,14 +446,11 @@ pub fn pong_game() -> Result<(), JsValue> {
         *g.borrow_mut() = Some(Closure::new(move || {
             let mut game = game_animation.borrow_mut();
 
-            context.clear_rect(
-                game.constraints.x1,
-                game.constraints.y1,
-                game.constraints.x2,
-                game.constraints.y2,
-            );
+            context.clear_rect(0.0, 0.0, WIDTH, HEIGHT);
 
-            game.move_ball();
+            if let Some(performance) = window().performance() {
+                game.frame(performance.now());
+            }
             game.draw(&context);
             request_animation_frame(f.borrow().as_ref().unwrap());
         }));
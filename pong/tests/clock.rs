@@ -0,0 +1,71 @@
+use pong::clock::{advance, blend, frame_time, MAX_FRAME_MS, STEP_MS};
+use pong_protocol::{Position, Snapshot};
+
+fn snapshot(x: f64, y: f64) -> Snapshot {
+    Snapshot {
+        ball: Position::new(x, y),
+        paddles: (Position::new(0.0, y), Position::new(x, 0.0)),
+    }
+}
+
+#[test]
+fn the_first_frame_takes_no_time() {
+    assert_eq!(frame_time(None, 1234.0), 0.0);
+}
+
+#[test]
+fn a_long_frame_is_clamped() {
+    assert_eq!(frame_time(Some(0.0), 10_000.0), MAX_FRAME_MS);
+    assert_eq!(frame_time(Some(100.0), 100.0 + MAX_FRAME_MS), MAX_FRAME_MS);
+}
+
+#[test]
+fn a_clock_running_backwards_takes_no_time() {
+    assert_eq!(frame_time(Some(500.0), 400.0), 0.0);
+}
+
+#[test]
+fn a_short_frame_carries_over_without_a_step() {
+    let (steps, accumulator) = advance(0.0, STEP_MS / 2.0);
+    assert_eq!(steps, 0);
+    assert_eq!(accumulator, STEP_MS / 2.0);
+
+    let (steps, accumulator) = advance(accumulator, STEP_MS / 2.0);
+    assert_eq!(steps, 1);
+    assert!(accumulator.abs() < 1e-9, "{accumulator}");
+}
+
+#[test]
+fn a_long_frame_catches_up_in_several_steps() {
+    let (steps, accumulator) = advance(0.0, frame_time(Some(0.0), 10_000.0));
+    assert!(steps > 1, "{steps}");
+    assert_eq!((steps, accumulator), advance(0.0, MAX_FRAME_MS));
+    assert!((0.0..STEP_MS).contains(&accumulator), "{accumulator}");
+    let total = f64::from(steps) * STEP_MS + accumulator;
+    assert!((total - MAX_FRAME_MS).abs() < 1e-9, "{total}");
+}
+
+#[test]
+fn blending_at_no_time_draws_the_previous_step() {
+    let (previous, current) = (snapshot(0.0, 10.0), snapshot(100.0, 30.0));
+    assert_eq!(blend(&previous, &current, 0.0), previous);
+}
+
+#[test]
+fn blending_halfway_draws_the_midpoint() {
+    let (previous, current) = (snapshot(0.0, 10.0), snapshot(100.0, 30.0));
+    assert_eq!(blend(&previous, &current, STEP_MS / 2.0), snapshot(50.0, 20.0));
+}
+
+#[test]
+fn blending_at_a_whole_step_draws_the_current_step() {
+    let (previous, current) = (snapshot(0.0, 10.0), snapshot(100.0, 30.0));
+    assert_eq!(blend(&previous, &current, STEP_MS), current);
+}
+
+#[test]
+fn blending_never_overshoots_either_step() {
+    let (previous, current) = (snapshot(0.0, 10.0), snapshot(100.0, 30.0));
+    assert_eq!(blend(&previous, &current, STEP_MS * 3.0), current);
+    assert_eq!(blend(&previous, &current, -STEP_MS), previous);
+}
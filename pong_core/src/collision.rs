@@ -0,0 +1,181 @@
+use std::ops::{Add, Mul, Sub};
+
+use pong_protocol::Position;
+
+// Slack for a ball resting exactly against a surface.
+const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vector {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vector {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Vector { x, y }
+    }
+
+    pub fn dot(self, other: Vector) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    /// Mirrors the vector off a surface with the unit `normal`.
+    pub fn reflect(self, normal: Vector) -> Vector {
+        self - normal * (2.0 * self.dot(normal))
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+
+    fn add(self, other: Vector) -> Vector {
+        Vector::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+
+    fn sub(self, other: Vector) -> Vector {
+        Vector::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f64> for Vector {
+    type Output = Vector;
+
+    fn mul(self, factor: f64) -> Vector {
+        Vector::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl From<Position> for Vector {
+    fn from(position: Position) -> Self {
+        Vector::new(position.x, position.y)
+    }
+}
+
+impl From<Vector> for Position {
+    fn from(vector: Vector) -> Self {
+        Position::new(vector.x, vector.y)
+    }
+}
+
+/// The first touch of a moving circle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// How far along the move, from 0 to 1.
+    pub time: f64,
+    /// Unit normal of the surface at the touch, pointing back at the circle.
+    pub normal: Vector,
+}
+
+fn earliest(first: Option<Hit>, second: Option<Hit>) -> Option<Hit> {
+    match (first, second) {
+        (Some(a), Some(b)) => Some(if b.time < a.time { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Segment {
+    pub a: Vector,
+    pub b: Vector,
+}
+
+impl Segment {
+    pub const fn new(a: Vector, b: Vector) -> Self {
+        Segment { a, b }
+    }
+
+    /// Where a circle of `radius` at `from`, moving by `motion`, first touches
+    /// the segment. Touches while moving away, or from inside, do not count.
+    pub fn sweep(&self, from: Vector, motion: Vector, radius: f64) -> Option<Hit> {
+        let along = self.b - self.a;
+        let length = along.length();
+        let mut first = None;
+        if length > 0.0 {
+            let normal = Vector::new(-along.y, along.x) * (1.0 / length);
+            for normal in [normal, normal * -1.0] {
+                // The circle's edge against a line `radius` in front of this side.
+                let gap = (from - self.a).dot(normal) - radius;
+                let closing = motion.dot(normal);
+                if closing >= 0.0 || gap < -EPSILON {
+                    continue;
+                }
+                let time = (gap / -closing).max(0.0);
+                if time > 1.0 {
+                    continue;
+                }
+                let at = from + motion * time;
+                let position = (at - self.a).dot(along) / (length * length);
+                if (0.0..=1.0).contains(&position) {
+                    first = earliest(first, Some(Hit { time, normal }));
+                }
+            }
+        }
+        // Past either end, the circle meets the end point itself.
+        for end in [self.a, self.b] {
+            first = earliest(first, sweep_point(end, from, motion, radius));
+        }
+        first
+    }
+}
+
+fn sweep_point(point: Vector, from: Vector, motion: Vector, radius: f64) -> Option<Hit> {
+    let offset = from - point;
+    let a = motion.dot(motion);
+    let half_b = offset.dot(motion);
+    let c = offset.dot(offset) - radius * radius;
+    if a == 0.0 || half_b >= 0.0 || c < -EPSILON {
+        return None;
+    }
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let time = ((-half_b - discriminant.sqrt()) / a).max(0.0);
+    if time > 1.0 {
+        return None;
+    }
+    let at = from + motion * time;
+    Some(Hit {
+        time,
+        normal: (at - point) * (1.0 / (at - point).length()),
+    })
+}
+
+/// An axis aligned rectangle.
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub min: Vector,
+    pub max: Vector,
+}
+
+impl Rect {
+    pub fn sweep(&self, from: Vector, motion: Vector, radius: f64) -> Option<Hit> {
+        let corners = [
+            self.min,
+            Vector::new(self.max.x, self.min.y),
+            self.max,
+            Vector::new(self.min.x, self.max.y),
+        ];
+        (0..4)
+            .map(|i| Segment::new(corners[i], corners[(i + 1) % 4]).sweep(from, motion, radius))
+            .fold(None, earliest)
+    }
+
+    /// Whether a circle at `center` overlaps the rectangle.
+    pub fn overlaps(&self, center: Vector, radius: f64) -> bool {
+        let nearest = Vector::new(
+            center.x.clamp(self.min.x, self.max.x),
+            center.y.clamp(self.min.y, self.max.y),
+        );
+        (center - nearest).length() < radius - EPSILON
+    }
+}
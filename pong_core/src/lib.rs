@@ -0,0 +1,476 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use pong_protocol::{Direction, Phase, Position, Side, Snapshot};
+
+use crate::collision::{Hit, Rect, Segment, Vector};
+
+mod collision;
+
+pub const WIDTH: f64 = 500.0;
+pub const HEIGHT: f64 = 300.0;
+pub const PADDLE_WIDTH: f64 = 12.0;
+pub const PADDLE_HEIGHT: f64 = 80.0;
+pub const BALL_RADIUS: f64 = 6.0;
+/// How often the game steps, on the server and in the browser alike.
+pub const STEPS_PER_SECOND: u32 = 60;
+
+// Steps spent in each phase before the match moves on.
+const COUNTDOWN_STEPS: u32 = 3 * STEPS_PER_SECOND;
+const SERVE_STEPS: u32 = STEPS_PER_SECOND / 2;
+const POINT_STEPS: u32 = STEPS_PER_SECOND;
+// Keys still held from the last rally do not start a rematch.
+const GAME_OVER_STEPS: u32 = 2 * STEPS_PER_SECOND;
+// How far a paddle moves for one input.
+const PADDLE_STEP: f64 = 30.0;
+
+const CENTER: Position = Position {
+    x: WIDTH / 2.0,
+    y: HEIGHT / 2.0,
+};
+
+// Bounces the ball can take within one step; the rest of a pathological move
+// is dropped rather than letting the ball slip out of the field.
+const MAX_CONTACTS: usize = 8;
+
+const TOP_WALL: Segment = Segment::new(Vector::new(0.0, 0.0), Vector::new(WIDTH, 0.0));
+const BOTTOM_WALL: Segment = Segment::new(Vector::new(0.0, HEIGHT), Vector::new(WIDTH, HEIGHT));
+const LEFT_GOAL: Segment = Segment::new(Vector::new(0.0, 0.0), Vector::new(0.0, HEIGHT));
+const RIGHT_GOAL: Segment = Segment::new(Vector::new(WIDTH, 0.0), Vector::new(WIDTH, HEIGHT));
+
+#[derive(Clone, Debug)]
+struct Paddle {
+    position: Position,
+    side: Side,
+}
+
+impl Paddle {
+    fn rect(&self) -> Rect {
+        let Position { x, y } = self.position;
+        Rect {
+            min: Vector::new(x, y),
+            max: Vector::new(x + PADDLE_WIDTH, y + PADDLE_HEIGHT),
+        }
+    }
+
+    /// Which way along x the paddle sends the ball.
+    fn away(&self) -> f64 {
+        match self.side {
+            Side::Left => 1.0,
+            Side::Right => -1.0,
+        }
+    }
+}
+
+/// What the ball can run into.
+#[derive(Clone, Copy, Debug)]
+enum Surface {
+    Paddle(Side),
+    Wall,
+    Goal(Side),
+}
+
+/// The paddle moves for one step, at most one per side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    pub left: Option<Direction>,
+    pub right: Option<Direction>,
+}
+
+impl Inputs {
+    /// Records `direction` for `side`, replacing an earlier one.
+    pub fn press(&mut self, side: Side, direction: Direction) {
+        match side {
+            Side::Left => self.left = Some(direction),
+            Side::Right => self.right = Some(direction),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Points a side needs to win the match.
+    pub points_to_win: u32,
+    /// Ball speed at each serve, in pixels per step.
+    pub serve_speed: f64,
+    /// Speed the ball gains from every paddle hit, up to `max_speed`.
+    pub speed_increment: f64,
+    pub max_speed: f64,
+    /// Angle from the horizontal, in degrees, of a ball returned off the very
+    /// end of a paddle. Hits closer to the middle return flatter.
+    pub max_bounce_angle: f64,
+    /// Share of the paddle's own movement passed on to the ball, 0 for none.
+    pub spin: f64,
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.points_to_win == 0 {
+            return Err(String::from("points_to_win must be at least 1"));
+        }
+        let numbers = [
+            ("serve_speed", self.serve_speed),
+            ("speed_increment", self.speed_increment),
+            ("max_speed", self.max_speed),
+            ("max_bounce_angle", self.max_bounce_angle),
+            ("spin", self.spin),
+        ];
+        if let Some((name, _)) = numbers.iter().find(|(_, number)| !number.is_finite()) {
+            return Err(format!("{name} must be a finite number"));
+        }
+        if self.serve_speed <= 0.0 {
+            return Err(String::from("serve_speed must be above 0"));
+        }
+        if self.speed_increment < 0.0 {
+            return Err(String::from("speed_increment must not be negative"));
+        }
+        if self.max_speed < self.serve_speed {
+            return Err(String::from("max_speed must be at least serve_speed"));
+        }
+        if !(self.max_bounce_angle > 0.0 && self.max_bounce_angle < 90.0) {
+            return Err(String::from(
+                "max_bounce_angle must be between 0 and 90 degrees",
+            ));
+        }
+        if self.spin < 0.0 {
+            return Err(String::from("spin must not be negative"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            points_to_win: 5,
+            // The original 45 degree serve at 6 pixels along each axis.
+            serve_speed: 6.0 * std::f64::consts::SQRT_2,
+            speed_increment: 0.5,
+            max_speed: 14.0,
+            max_bounce_angle: 60.0,
+            spin: 0.3,
+        }
+    }
+}
+
+/// The Pong simulation, without any rendering, advanced one [`Game::step`] at a time.
+#[derive(Clone, Debug)]
+pub struct Game {
+    settings: Settings,
+    phase: Phase,
+    /// Steps left before the phase moves on.
+    timer: u32,
+    serves: u32,
+    ball: Position,
+    /// Unit vector the ball travels along.
+    ball_direction_x: f64,
+    ball_direction_y: f64,
+    paddles: (Paddle, Paddle),
+    /// How far each paddle moved in the current step, for spin.
+    paddle_moves: (f64, f64),
+    speed: f64,
+    scores: (u32, u32),
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::with_settings(Settings::default())
+    }
+
+    /// A game in the lobby, waiting for [`Game::start`].
+    pub fn with_settings(settings: Settings) -> Self {
+        let paddle_one = Paddle {
+            position: Position::new(0.0, 0.0),
+            side: Side::Left,
+        };
+        let paddle_two = Paddle {
+            position: Position::new(WIDTH - PADDLE_WIDTH, HEIGHT - PADDLE_HEIGHT),
+            side: Side::Right,
+        };
+
+        Game {
+            settings,
+            phase: Phase::Lobby,
+            timer: 0,
+            serves: 0,
+            ball: CENTER,
+            ball_direction_x: FRAC_1_SQRT_2,
+            ball_direction_y: FRAC_1_SQRT_2,
+            paddles: (paddle_one, paddle_two),
+            paddle_moves: (0.0, 0.0),
+            speed: settings.serve_speed,
+            scores: (0, 0),
+        }
+    }
+
+    /// Starts a match, from the lobby or once the last one is over, with a
+    /// countdown to the first serve.
+    pub fn start(&mut self) {
+        self.scores = (0, 0);
+        self.serves = 0;
+        self.ball = CENTER;
+        self.enter(Phase::Countdown { seconds: 0 }, COUNTDOWN_STEPS);
+    }
+
+    /// Moves the paddles by `inputs`, then the match along. Returns the side
+    /// that lost a point, if any.
+    pub fn step(&mut self, inputs: Inputs) -> Option<Side> {
+        self.paddle_moves = (
+            inputs
+                .left
+                .map_or(0.0, |direction| self.move_paddle(direction, Side::Left)),
+            inputs
+                .right
+                .map_or(0.0, |direction| self.move_paddle(direction, Side::Right)),
+        );
+
+        match self.phase {
+            Phase::Lobby => None,
+            Phase::Rally => {
+                let lost = self.move_ball();
+                if let Some(loser) = lost {
+                    self.point(loser.opponent());
+                }
+                lost
+            }
+            Phase::GameOver { .. } if self.timer == 0 => {
+                if inputs != Inputs::default() {
+                    self.start();
+                }
+                None
+            }
+            _ => {
+                self.tick();
+                None
+            }
+        }
+    }
+
+    fn enter(&mut self, phase: Phase, steps: u32) {
+        self.phase = phase;
+        self.timer = steps;
+        if let Phase::Countdown { seconds } = &mut self.phase {
+            *seconds = steps.div_ceil(STEPS_PER_SECOND);
+        }
+    }
+
+    fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if let Phase::Countdown { seconds } = &mut self.phase {
+            *seconds = self.timer.div_ceil(STEPS_PER_SECOND);
+        }
+        if self.timer > 0 {
+            return;
+        }
+        match self.phase {
+            Phase::Countdown { .. } => self.serve(Side::Right),
+            Phase::Serve { toward } => {
+                // Alternate serving up and down, at 45 degrees.
+                self.ball_direction_x = match toward {
+                    Side::Left => -FRAC_1_SQRT_2,
+                    Side::Right => FRAC_1_SQRT_2,
+                };
+                self.ball_direction_y = if self.serves.is_multiple_of(2) {
+                    FRAC_1_SQRT_2
+                } else {
+                    -FRAC_1_SQRT_2
+                };
+                self.speed = self.settings.serve_speed;
+                self.serves += 1;
+                self.phase = Phase::Rally;
+            }
+            // The side that lost the point receives the next serve.
+            Phase::Point { scorer } => self.serve(scorer.opponent()),
+            Phase::Lobby | Phase::Rally | Phase::GameOver { .. } => {}
+        }
+    }
+
+    fn serve(&mut self, toward: Side) {
+        self.ball = CENTER;
+        self.enter(Phase::Serve { toward }, SERVE_STEPS);
+    }
+
+    fn point(&mut self, scorer: Side) {
+        let points = match scorer {
+            Side::Left => &mut self.scores.0,
+            Side::Right => &mut self.scores.1,
+        };
+        *points += 1;
+        if *points >= self.settings.points_to_win {
+            self.enter(Phase::GameOver { winner: scorer }, GAME_OVER_STEPS);
+        } else {
+            self.enter(Phase::Point { scorer }, POINT_STEPS);
+        }
+    }
+
+    /// Sweeps the ball along its path for one step, bouncing off whatever it
+    /// touches first and carrying on with the rest of the move. Returns the side
+    /// that lost the point if it reached a goal line.
+    fn move_ball(&mut self) -> Option<Side> {
+        self.free_ball();
+        let mut remaining = 1.0;
+        for _ in 0..MAX_CONTACTS {
+            let from = Vector::from(self.ball);
+            let direction = Vector::new(self.ball_direction_x, self.ball_direction_y);
+            let motion = direction * (self.speed * remaining);
+            let Some((hit, surface)) = self.first_contact(from, motion) else {
+                self.ball = (from + motion).into();
+                return None;
+            };
+            self.ball = (from + motion * hit.time).into();
+            remaining *= 1.0 - hit.time;
+            match surface {
+                Surface::Goal(side) => return Some(side),
+                Surface::Paddle(side) if self.faces(side, hit.normal) => self.hit(side),
+                Surface::Paddle(_) | Surface::Wall => {
+                    let bounced = direction.reflect(hit.normal);
+                    self.ball_direction_x = bounced.x;
+                    self.ball_direction_y = bounced.y;
+                }
+            }
+        }
+        None
+    }
+
+    /// A paddle can move onto the ball. In front of the paddle the ball is
+    /// pushed back out of its face, and returned if it was heading in; behind
+    /// it the point is lost anyway.
+    fn free_ball(&mut self) {
+        for side in [Side::Left, Side::Right] {
+            let paddle = self.paddle_for(side);
+            let rect = paddle.rect();
+            let away = paddle.away();
+            let middle = (rect.min.x + rect.max.x) / 2.0;
+            if !rect.overlaps(self.ball.into(), BALL_RADIUS) || (self.ball.x - middle) * away < 0.0
+            {
+                continue;
+            }
+            self.ball.x = middle + away * (PADDLE_WIDTH / 2.0 + BALL_RADIUS);
+            if self.ball_direction_x * away < 0.0 {
+                self.hit(side);
+            }
+        }
+    }
+
+    fn first_contact(&self, from: Vector, motion: Vector) -> Option<(Hit, Surface)> {
+        // Ties go to the earlier entry, so a paddle beats the goal line behind it.
+        let surfaces = [
+            (
+                self.paddles.0.rect().sweep(from, motion, BALL_RADIUS),
+                Surface::Paddle(Side::Left),
+            ),
+            (
+                self.paddles.1.rect().sweep(from, motion, BALL_RADIUS),
+                Surface::Paddle(Side::Right),
+            ),
+            (TOP_WALL.sweep(from, motion, BALL_RADIUS), Surface::Wall),
+            (BOTTOM_WALL.sweep(from, motion, BALL_RADIUS), Surface::Wall),
+            (
+                LEFT_GOAL.sweep(from, motion, BALL_RADIUS),
+                Surface::Goal(Side::Left),
+            ),
+            (
+                RIGHT_GOAL.sweep(from, motion, BALL_RADIUS),
+                Surface::Goal(Side::Right),
+            ),
+        ];
+        surfaces
+            .into_iter()
+            .filter_map(|(hit, surface)| Some((hit?, surface)))
+            .min_by(|a, b| a.0.time.total_cmp(&b.0.time))
+    }
+
+    /// Whether a touch with `normal` is on the paddle's face, including the
+    /// rounded part of its corners that still faces the field.
+    fn faces(&self, side: Side, normal: Vector) -> bool {
+        normal.x * self.paddle_for(side).away() >= normal.y.abs()
+    }
+
+    fn paddle_for(&self, side: Side) -> &Paddle {
+        match side {
+            Side::Left => &self.paddles.0,
+            Side::Right => &self.paddles.1,
+        }
+    }
+
+    /// Returns the ball off `side`'s paddle, steeper the further from the
+    /// middle it hits, and faster with every hit.
+    fn hit(&mut self, side: Side) {
+        let (paddle, moved, away) = match side {
+            Side::Left => (&self.paddles.0, self.paddle_moves.0, 1.0),
+            Side::Right => (&self.paddles.1, self.paddle_moves.1, -1.0),
+        };
+        let half = PADDLE_HEIGHT / 2.0;
+        // -1 at the top end of the paddle, 1 at the bottom.
+        let offset = ((self.ball.y - (paddle.position.y + half)) / half).clamp(-1.0, 1.0);
+        let max_angle = self.settings.max_bounce_angle.to_radians();
+
+        self.speed = (self.speed + self.settings.speed_increment).min(self.settings.max_speed);
+        // Spin bends the return, but never past the steepest angle.
+        let limit = max_angle.sin() * self.speed;
+        let velocity_y = ((offset * max_angle).sin() * self.speed + self.settings.spin * moved)
+            .clamp(-limit, limit);
+        self.ball_direction_y = velocity_y / self.speed;
+        self.ball_direction_x = away * (1.0 - self.ball_direction_y.powi(2)).sqrt();
+    }
+
+    /// Moves the paddle one step, stopping at the edges, and returns how far it went.
+    fn move_paddle(&mut self, direction: Direction, side: Side) -> f64 {
+        let paddle = match side {
+            Side::Left => &mut self.paddles.0,
+            Side::Right => &mut self.paddles.1,
+        };
+        let step = match direction {
+            Direction::Up => -PADDLE_STEP,
+            Direction::Down => PADDLE_STEP,
+        };
+        let before = paddle.position.y;
+        paddle.position.y = (before + step).clamp(0.0, HEIGHT - PADDLE_HEIGHT);
+        paddle.position.y - before
+    }
+
+    pub fn ball(&self) -> Position {
+        self.ball
+    }
+
+    /// Top left corner of the paddle.
+    pub fn paddle(&self, side: Side) -> Position {
+        match side {
+            Side::Left => self.paddles.0.position,
+            Side::Right => self.paddles.1.position,
+        }
+    }
+
+    /// Points won by each side.
+    pub fn scores(&self) -> (u32, u32) {
+        self.scores
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ball: self.ball,
+            paddles: (self.paddles.0.position, self.paddles.1.position),
+        }
+    }
+
+    /// Moves the ball and paddles to where `snapshot` has them, keeping the
+    /// ball's direction.
+    pub fn apply(&mut self, snapshot: &Snapshot) {
+        self.ball = snapshot.ball;
+        self.paddles.0.position = snapshot.paddles.0;
+        self.paddles.1.position = snapshot.paddles.1;
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
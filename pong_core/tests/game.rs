@@ -0,0 +1,507 @@
+use pong_core::{Game, Inputs, Settings, BALL_RADIUS, HEIGHT, PADDLE_HEIGHT, PADDLE_WIDTH, WIDTH};
+use pong_protocol::{Direction, Phase, Position, Side, Snapshot};
+
+/// Steps `game` until the next rally starts.
+fn play_until_rally(game: &mut Game) {
+    for _ in 0..1000 {
+        if game.phase() == Phase::Rally {
+            return;
+        }
+        game.step(Inputs::default());
+    }
+    panic!("no rally started, stuck in {:?}", game.phase());
+}
+
+/// A rally on the first serve, heading right and down, with the ball moved to `ball`.
+fn game_with_ball(ball: Position) -> Game {
+    rally_with(Settings::default(), ball)
+}
+
+fn rally_with(settings: Settings, ball: Position) -> Game {
+    let mut game = Game::with_settings(settings);
+    game.start();
+    play_until_rally(&mut game);
+    let mut snapshot = game.snapshot();
+    snapshot.ball = ball;
+    game.apply(&snapshot);
+    game
+}
+
+fn holding(side: Side, direction: Direction) -> Inputs {
+    let mut inputs = Inputs::default();
+    inputs.press(side, direction);
+    inputs
+}
+
+#[test]
+fn ball_bounces_off_the_bottom_and_top_walls() {
+    let mut game = game_with_ball(Position::new(100.0, HEIGHT - BALL_RADIUS));
+
+    game.step(Inputs::default());
+    let mut previous = game.ball();
+    assert!(
+        previous.y < HEIGHT - BALL_RADIUS,
+        "ball should head up after the bottom wall"
+    );
+
+    let mut lowest = previous.y;
+    loop {
+        game.step(Inputs::default());
+        let ball = game.ball();
+        lowest = lowest.min(ball.y);
+        if ball.y > previous.y {
+            break;
+        }
+        previous = ball;
+    }
+    // The ball turns with its edge on the wall, mid step.
+    assert!(
+        lowest >= BALL_RADIUS - 1e-9 && lowest < BALL_RADIUS + game.settings().max_speed,
+        "turned at y = {lowest}"
+    );
+    assert_eq!(game.scores(), (0, 0));
+}
+
+#[test]
+fn ball_bounces_off_a_paddle() {
+    let mut game = game_with_ball(Position::new(WIDTH - PADDLE_WIDTH - 6.0, 100.0));
+    let mut snapshot = game.snapshot();
+    snapshot.paddles.1 = Position::new(WIDTH - PADDLE_WIDTH, 60.0);
+    game.apply(&snapshot);
+
+    let before = game.ball();
+    assert_eq!(game.step(Inputs::default()), None);
+    assert!(
+        game.ball().x < before.x,
+        "ball should head left after the paddle"
+    );
+    for _ in 0..10 {
+        let before = game.ball();
+        game.step(Inputs::default());
+        assert!(game.ball().x < before.x);
+    }
+    assert_eq!(game.scores(), (0, 0));
+}
+
+#[test]
+fn missing_the_ball_costs_a_point() {
+    // The right paddle starts at the bottom, well below the ball.
+    let mut game = game_with_ball(Position::new(WIDTH - 6.0, 100.0));
+
+    assert_eq!(game.step(Inputs::default()), Some(Side::Right));
+    assert_eq!(game.scores(), (1, 0));
+    assert_eq!(game.phase(), Phase::Point { scorer: Side::Left });
+}
+
+#[test]
+fn ball_returned_past_the_left_paddle_costs_left_a_point() {
+    let mut game = game_with_ball(Position::new(WIDTH - PADDLE_WIDTH - 6.0, 100.0));
+    let mut snapshot = game.snapshot();
+    snapshot.paddles.1 = Position::new(WIDTH - PADDLE_WIDTH, 60.0);
+    game.apply(&snapshot);
+
+    let lost = (0..200)
+        .find_map(|_| game.step(holding(Side::Left, Direction::Down)))
+        .expect("left should miss with its paddle at the bottom");
+    assert_eq!(lost, Side::Left);
+    assert_eq!(game.scores(), (0, 1));
+}
+
+#[test]
+fn steps_without_a_point_return_none() {
+    let mut game = Game::new();
+    game.start();
+    play_until_rally(&mut game);
+    for _ in 0..10 {
+        assert_eq!(game.step(Inputs::default()), None);
+    }
+    assert_eq!(game.scores(), (0, 0));
+}
+
+#[test]
+fn paddles_stay_on_the_field() {
+    let mut game = Game::new();
+    let both = |direction| Inputs {
+        left: Some(direction),
+        right: Some(direction),
+    };
+
+    for _ in 0..50 {
+        game.step(both(Direction::Up));
+        for side in [Side::Left, Side::Right] {
+            assert!(game.paddle(side).y >= 0.0);
+        }
+    }
+    for _ in 0..50 {
+        game.step(both(Direction::Down));
+        for side in [Side::Left, Side::Right] {
+            assert!(game.paddle(side).y <= HEIGHT - PADDLE_HEIGHT);
+        }
+    }
+}
+
+#[test]
+fn paddles_move_only_on_input() {
+    let mut game = Game::new();
+    let (left, right) = (game.paddle(Side::Left), game.paddle(Side::Right));
+
+    game.step(Inputs::default());
+    assert_eq!(game.paddle(Side::Left), left);
+    assert_eq!(game.paddle(Side::Right), right);
+
+    game.step(holding(Side::Left, Direction::Down));
+    assert!(game.paddle(Side::Left).y > left.y);
+    assert_eq!(game.paddle(Side::Right), right);
+
+    game.step(holding(Side::Right, Direction::Up));
+    assert!(game.paddle(Side::Right).y < right.y);
+}
+
+#[test]
+fn paddles_keep_their_column() {
+    let mut game = Game::new();
+    for _ in 0..20 {
+        game.step(Inputs {
+            left: Some(Direction::Down),
+            right: Some(Direction::Up),
+        });
+    }
+    assert_eq!(game.paddle(Side::Left).x, 0.0);
+    assert_eq!(game.paddle(Side::Right).x, WIDTH - PADDLE_WIDTH);
+}
+
+#[test]
+fn later_presses_replace_earlier_ones() {
+    let mut inputs = holding(Side::Left, Direction::Up);
+    inputs.press(Side::Left, Direction::Down);
+    assert_eq!(inputs.left, Some(Direction::Down));
+    assert_eq!(inputs.right, None);
+}
+
+#[test]
+fn apply_restores_a_snapshot() {
+    let mut game = Game::new();
+    for _ in 0..5 {
+        game.step(holding(Side::Left, Direction::Down));
+    }
+    let snapshot = game.snapshot();
+
+    let mut other = Game::new();
+    other.apply(&snapshot);
+    assert_eq!(other.snapshot(), snapshot);
+    assert_eq!(
+        Snapshot {
+            ball: other.ball(),
+            paddles: (other.paddle(Side::Left), other.paddle(Side::Right)),
+        },
+        snapshot
+    );
+}
+
+#[test]
+fn the_lobby_waits_for_start() {
+    let mut game = Game::new();
+    let ball = game.ball();
+    for _ in 0..100 {
+        game.step(Inputs::default());
+    }
+    assert_eq!(game.phase(), Phase::Lobby);
+    assert_eq!(game.ball(), ball);
+}
+
+#[test]
+fn a_match_counts_down_then_serves() {
+    let mut game = Game::new();
+    game.start();
+    assert_eq!(game.phase(), Phase::Countdown { seconds: 3 });
+
+    let mut seconds = vec![3];
+    while let Phase::Countdown { seconds: left } = game.phase() {
+        if seconds.last() != Some(&left) {
+            seconds.push(left);
+        }
+        game.step(Inputs::default());
+    }
+    assert_eq!(seconds, [3, 2, 1]);
+    assert_eq!(
+        game.phase(),
+        Phase::Serve {
+            toward: Side::Right
+        }
+    );
+    assert_eq!(game.ball(), Position::new(WIDTH / 2.0, HEIGHT / 2.0));
+
+    game.step(Inputs::default());
+    assert_eq!(game.ball(), Position::new(WIDTH / 2.0, HEIGHT / 2.0));
+    play_until_rally(&mut game);
+    let before = game.ball();
+    game.step(Inputs::default());
+    assert!(game.ball().x > before.x, "the first serve heads right");
+}
+
+#[test]
+fn the_ball_is_served_toward_the_side_that_lost_the_point() {
+    let mut game = game_with_ball(Position::new(WIDTH - 6.0, 100.0));
+    assert_eq!(game.step(Inputs::default()), Some(Side::Right));
+
+    let ball = game.ball();
+    game.step(Inputs::default());
+    assert_eq!(game.ball(), ball, "the ball rests while the point shows");
+
+    while let Phase::Point { .. } = game.phase() {
+        game.step(Inputs::default());
+    }
+    assert_eq!(
+        game.phase(),
+        Phase::Serve {
+            toward: Side::Right
+        }
+    );
+    assert_eq!(game.ball(), Position::new(WIDTH / 2.0, HEIGHT / 2.0));
+
+    play_until_rally(&mut game);
+    let before = game.ball();
+    game.step(Inputs::default());
+    assert!(game.ball().x > before.x);
+    assert!(game.ball().y < before.y, "serves alternate up and down");
+}
+
+/// Plays until someone wins, with both paddles parked out of the ball's way.
+fn play_out(game: &mut Game) -> Side {
+    let mut snapshot = game.snapshot();
+    snapshot.paddles = (
+        Position::new(0.0, 0.0),
+        Position::new(WIDTH - PADDLE_WIDTH, 0.0),
+    );
+    game.apply(&snapshot);
+    for _ in 0..100_000 {
+        if let Phase::GameOver { winner } = game.phase() {
+            return winner;
+        }
+        game.step(Inputs::default());
+        // Keep the ball clear of the paddles so every rally ends in a point.
+        if game.phase() == Phase::Rally && game.ball().y < PADDLE_HEIGHT + 10.0 {
+            let mut snapshot = game.snapshot();
+            snapshot.ball.y = HEIGHT / 2.0;
+            game.apply(&snapshot);
+        }
+    }
+    panic!("the match never ended");
+}
+
+#[test]
+fn the_match_ends_at_points_to_win() {
+    let mut game = Game::with_settings(Settings {
+        points_to_win: 3,
+        ..Settings::default()
+    });
+    game.start();
+    let winner = play_out(&mut game);
+
+    let (left, right) = game.scores();
+    let (won, lost) = match winner {
+        Side::Left => (left, right),
+        Side::Right => (right, left),
+    };
+    assert_eq!(won, 3);
+    assert!(lost < 3);
+
+    let snapshot = game.snapshot();
+    for _ in 0..100 {
+        game.step(Inputs::default());
+    }
+    assert_eq!(game.phase(), Phase::GameOver { winner });
+    assert_eq!(game.snapshot().ball, snapshot.ball);
+}
+
+#[test]
+fn a_key_after_game_over_starts_a_rematch() {
+    let mut game = Game::with_settings(Settings {
+        points_to_win: 1,
+        ..Settings::default()
+    });
+    game.start();
+    let winner = play_out(&mut game);
+
+    // Held keys right after the winning point are ignored.
+    game.step(holding(Side::Left, Direction::Down));
+    assert_eq!(game.phase(), Phase::GameOver { winner });
+
+    for _ in 0..1000 {
+        game.step(Inputs::default());
+    }
+    game.step(holding(Side::Left, Direction::Up));
+    assert_eq!(game.phase(), Phase::Countdown { seconds: 3 });
+    assert_eq!(game.scores(), (0, 0));
+}
+
+/// A rally with the right paddle's top at `paddle_y` and the ball heading
+/// into it at `ball_y`.
+fn about_to_hit_right(settings: Settings, paddle_y: f64, ball_y: f64) -> Game {
+    let mut game = rally_with(settings, Position::new(WIDTH - PADDLE_WIDTH - 6.0, ball_y));
+    let mut snapshot = game.snapshot();
+    snapshot.paddles.1 = Position::new(WIDTH - PADDLE_WIDTH, paddle_y);
+    game.apply(&snapshot);
+    game
+}
+
+/// How far the ball moves in one step, along each axis.
+fn ball_step(game: &mut Game, inputs: Inputs) -> (f64, f64) {
+    let before = game.ball();
+    game.step(inputs);
+    let after = game.ball();
+    (after.x - before.x, after.y - before.y)
+}
+
+fn speed((dx, dy): (f64, f64)) -> f64 {
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn angle((dx, dy): (f64, f64)) -> f64 {
+    dy.abs().atan2(dx.abs()).to_degrees()
+}
+
+#[test]
+fn hits_off_center_return_at_an_angle() {
+    let settings = Settings::default();
+    // The paddle spans 60 to 140, its middle is at 100.
+    let (dx, dy) = ball_step(
+        &mut about_to_hit_right(settings, 60.0, 100.0),
+        Inputs::default(),
+    );
+    assert!(dx < 0.0);
+    assert!(dy.abs() < 1e-9, "a hit in the middle returns flat");
+
+    let high = ball_step(
+        &mut about_to_hit_right(settings, 60.0, 70.0),
+        Inputs::default(),
+    );
+    let upper = ball_step(
+        &mut about_to_hit_right(settings, 60.0, 90.0),
+        Inputs::default(),
+    );
+    let low = ball_step(
+        &mut about_to_hit_right(settings, 60.0, 130.0),
+        Inputs::default(),
+    );
+    assert!(
+        high.1 < 0.0 && upper.1 < 0.0,
+        "hits above the middle return up"
+    );
+    assert!(low.1 > 0.0, "hits below the middle return down");
+    assert!(angle(high) > angle(upper), "further out returns steeper");
+    assert!(angle(high) < settings.max_bounce_angle);
+}
+
+#[test]
+fn every_hit_speeds_the_ball_up_to_the_cap() {
+    let settings = Settings {
+        serve_speed: 8.0,
+        speed_increment: 3.0,
+        max_speed: 12.0,
+        spin: 0.0,
+        ..Settings::default()
+    };
+    let mut game = about_to_hit_right(settings, 60.0, 100.0);
+    assert!((speed(ball_step(&mut game, Inputs::default())) - 11.0).abs() < 1e-9);
+
+    // Back across to the middle of the left paddle, at the top of the field.
+    let mut snapshot = game.snapshot();
+    snapshot.ball = Position::new(PADDLE_WIDTH + 6.0, PADDLE_HEIGHT / 2.0);
+    game.apply(&snapshot);
+    let returned = ball_step(&mut game, Inputs::default());
+    assert!(returned.0 > 0.0);
+    assert!((speed(returned) - 12.0).abs() < 1e-9);
+}
+
+#[test]
+fn each_serve_starts_at_serve_speed() {
+    let settings = Settings {
+        serve_speed: 8.0,
+        speed_increment: 3.0,
+        max_speed: 20.0,
+        ..Settings::default()
+    };
+    let mut game = about_to_hit_right(settings, 60.0, 100.0);
+    ball_step(&mut game, Inputs::default());
+
+    let lost = (0..500)
+        .find_map(|_| game.step(Inputs::default()))
+        .expect("the left paddle sits above the ball's path");
+    assert_eq!(lost, Side::Left);
+    play_until_rally(&mut game);
+    let served = ball_step(&mut game, Inputs::default());
+    assert!((speed(served) - 8.0).abs() < 1e-9);
+    assert!((angle(served) - 45.0).abs() < 1e-9);
+}
+
+#[test]
+fn a_moving_paddle_puts_spin_on_the_ball() {
+    let still = Settings {
+        spin: 0.0,
+        ..Settings::default()
+    };
+    let spinning = Settings {
+        spin: 0.5,
+        ..Settings::default()
+    };
+    // Moving down, the paddle meets the ball with its middle.
+    let down = holding(Side::Right, Direction::Down);
+    let plain = ball_step(&mut about_to_hit_right(still, 60.0, 130.0), down);
+    let spun = ball_step(&mut about_to_hit_right(spinning, 60.0, 130.0), down);
+    assert!(plain.1.abs() < 1e-9);
+    assert!(spun.1 > 0.0, "spin follows the paddle");
+    assert!(
+        (speed(spun) - speed(plain)).abs() < 1e-9,
+        "spin bends, not speeds"
+    );
+
+    let idle = ball_step(
+        &mut about_to_hit_right(spinning, 60.0, 100.0),
+        Inputs::default(),
+    );
+    assert!(idle.1.abs() < 1e-9, "a still paddle puts no spin on");
+}
+
+#[test]
+fn spin_never_steepens_past_the_largest_angle() {
+    let settings = Settings {
+        spin: 5.0,
+        ..Settings::default()
+    };
+    let up = holding(Side::Right, Direction::Up);
+    // Moving up to 30, the paddle meets the ball near its top end.
+    let returned = ball_step(&mut about_to_hit_right(settings, 60.0, 40.0), up);
+    assert!(returned.1 < 0.0);
+    assert!((angle(returned) - settings.max_bounce_angle).abs() < 1e-9);
+}
+
+#[test]
+fn settings_are_validated() {
+    assert_eq!(Settings::default().validate(), Ok(()));
+    let invalid = [
+        Settings {
+            points_to_win: 0,
+            ..Settings::default()
+        },
+        Settings {
+            serve_speed: 0.0,
+            ..Settings::default()
+        },
+        Settings {
+            max_speed: 1.0,
+            ..Settings::default()
+        },
+        Settings {
+            max_bounce_angle: 90.0,
+            ..Settings::default()
+        },
+        Settings {
+            spin: f64::NAN,
+            ..Settings::default()
+        },
+    ];
+    for settings in invalid {
+        assert!(settings.validate().is_err(), "{settings:?}");
+    }
+}
@@ -0,0 +1,117 @@
+use pong_core::{Game, Inputs, Settings, BALL_RADIUS, HEIGHT, PADDLE_HEIGHT, PADDLE_WIDTH, WIDTH};
+use pong_protocol::{Direction, Phase, Position};
+use proptest::prelude::*;
+
+// Room for rounding at the exact point of contact.
+const SLACK: f64 = 1e-6;
+
+fn settings() -> impl Strategy<Value = Settings> {
+    (
+        1u32..5,
+        1.0..60.0,
+        0.0..10.0,
+        0.0..200.0,
+        5.0..85.0,
+        0.0..2.0,
+    )
+        .prop_map(
+            |(points_to_win, serve_speed, speed_increment, headroom, max_bounce_angle, spin)| {
+                Settings {
+                    points_to_win,
+                    serve_speed,
+                    speed_increment,
+                    max_speed: serve_speed + headroom,
+                    max_bounce_angle,
+                    spin,
+                }
+            },
+        )
+}
+
+fn direction() -> impl Strategy<Value = Option<Direction>> {
+    prop_oneof![
+        Just(None),
+        Just(Some(Direction::Up)),
+        Just(Some(Direction::Down))
+    ]
+}
+
+fn inputs() -> impl Strategy<Value = Vec<Inputs>> {
+    prop::collection::vec(
+        (direction(), direction()).prop_map(|(left, right)| Inputs { left, right }),
+        0..1500,
+    )
+}
+
+proptest! {
+    #[test]
+    fn the_ball_never_leaves_the_field(settings in settings(), inputs in inputs()) {
+        let mut game = Game::with_settings(settings);
+        game.start();
+        let mut points = 0;
+        for inputs in inputs {
+            game.step(inputs);
+            let ball = game.ball();
+            prop_assert!(
+                (BALL_RADIUS - SLACK..=HEIGHT - BALL_RADIUS + SLACK).contains(&ball.y),
+                "ball at y = {}", ball.y
+            );
+            prop_assert!(
+                (BALL_RADIUS - SLACK..=WIDTH - BALL_RADIUS + SLACK).contains(&ball.x),
+                "ball at x = {}", ball.x
+            );
+
+            let (left, right) = game.scores();
+            if left + right < points {
+                // A rematch started.
+                points = 0;
+            }
+            prop_assert!(left + right <= points + 1, "more than one point in a step");
+            points = left + right;
+        }
+    }
+
+    #[test]
+    fn fast_balls_never_pass_a_paddle(
+        speed in 5.0..400.0,
+        right_y in 0.0..=HEIGHT - PADDLE_HEIGHT,
+        reach in 0.0..=PADDLE_HEIGHT,
+    ) {
+        let settings = Settings {
+            serve_speed: speed,
+            speed_increment: 0.0,
+            max_speed: speed,
+            spin: 0.0,
+            ..Settings::default()
+        };
+        let mut game = Game::with_settings(settings);
+        game.start();
+        while game.phase() != Phase::Rally {
+            game.step(Inputs::default());
+        }
+        // A flat return off the middle of the right paddle, straight at the left one.
+        let ball_y = right_y + PADDLE_HEIGHT / 2.0;
+        let left_y = (ball_y - reach).clamp(0.0, HEIGHT - PADDLE_HEIGHT);
+        let mut snapshot = game.snapshot();
+        snapshot.ball = Position::new(WIDTH - PADDLE_WIDTH - BALL_RADIUS, ball_y);
+        snapshot.paddles = (Position::new(0.0, left_y), Position::new(WIDTH - PADDLE_WIDTH, right_y));
+        game.apply(&snapshot);
+
+        // Off the right paddle, then back off the left one.
+        prop_assert_eq!(game.step(Inputs::default()), None);
+        let mut previous = game.ball().x;
+        let mut returned = false;
+        for _ in 0..(WIDTH / speed) as usize + 2 {
+            prop_assert_eq!(game.step(Inputs::default()), None);
+            let x = game.ball().x;
+            prop_assert!(x >= PADDLE_WIDTH + BALL_RADIUS - SLACK, "ball reached x = {}", x);
+            if x > previous {
+                returned = true;
+                break;
+            }
+            previous = x;
+        }
+        prop_assert!(returned, "ball never came back");
+        prop_assert_eq!(game.scores(), (0, 0));
+    }
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+/// Bumped on any incompatible change to the messages below. Clients send it
+/// when joining and the server refuses versions it does not speak.
+pub const VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Position {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub ball: Position,
+    pub paddles: (Position, Position),
+}
+
+/// Where a match is at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum Phase {
+    /// Waiting for both players.
+    Lobby,
+    /// Counting down to the first serve.
+    Countdown {
+        seconds: u32,
+    },
+    /// The ball waits in the middle, about to head for `toward`.
+    Serve {
+        toward: Side,
+    },
+    Rally,
+    /// `scorer` just won a point.
+    Point {
+        scorer: Side,
+    },
+    GameOver {
+        winner: Side,
+    },
+}
+
+/// Which room a client wants a seat in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// The room waiting for an opponent, or a new one.
+    Queue,
+    /// A new room to share the code of.
+    Create,
+    /// A free seat in the room with this code.
+    Room(String),
+    /// The seat the token was handed out for.
+    Rejoin { room: String, token: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join { version: u32, target: Target },
+    Input { direction: Direction },
+    Ping { nonce: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        version: u32,
+        room: String,
+        side: Side,
+        token: String,
+    },
+    Players {
+        left: bool,
+        right: bool,
+    },
+    State(Snapshot),
+    Phase(Phase),
+    /// Points won by each side.
+    Score {
+        left: u32,
+        right: u32,
+    },
+    Error {
+        message: String,
+    },
+    Pong {
+        nonce: u64,
+    },
+}
+
+impl ClientMessage {
+    pub fn decode(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("client messages serialize")
+    }
+}
+
+impl ServerMessage {
+    pub fn decode(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("server messages serialize")
+    }
+}
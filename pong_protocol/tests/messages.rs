@@ -0,0 +1,195 @@
+use pong_protocol::{
+    ClientMessage, Direction, Phase, Position, ServerMessage, Side, Snapshot, Target, VERSION,
+};
+use serde_json::{json, Value};
+
+fn client_messages() -> Vec<ClientMessage> {
+    let targets = [
+        Target::Queue,
+        Target::Create,
+        Target::Room("ab12".to_string()),
+        Target::Rejoin {
+            room: "ab12".to_string(),
+            token: "secret".to_string(),
+        },
+    ];
+    targets
+        .into_iter()
+        .map(|target| ClientMessage::Join {
+            version: VERSION,
+            target,
+        })
+        .chain([
+            ClientMessage::Input {
+                direction: Direction::Up,
+            },
+            ClientMessage::Input {
+                direction: Direction::Down,
+            },
+            ClientMessage::Ping { nonce: u64::MAX },
+        ])
+        .collect()
+}
+
+fn server_messages() -> Vec<ServerMessage> {
+    let phases = [
+        Phase::Lobby,
+        Phase::Countdown { seconds: 3 },
+        Phase::Serve { toward: Side::Left },
+        Phase::Rally,
+        Phase::Point {
+            scorer: Side::Right,
+        },
+        Phase::GameOver { winner: Side::Left },
+    ];
+    [
+        ServerMessage::Welcome {
+            version: VERSION,
+            room: "ab12".to_string(),
+            side: Side::Right,
+            token: "secret".to_string(),
+        },
+        ServerMessage::Players {
+            left: true,
+            right: false,
+        },
+        ServerMessage::State(Snapshot {
+            ball: Position::new(400.5, 300.25),
+            paddles: (Position::new(10.0, 250.0), Position::new(780.0, -0.5)),
+        }),
+        ServerMessage::Score { left: 4, right: 5 },
+        ServerMessage::Error {
+            message: "room \"ab12\" is full".to_string(),
+        },
+        ServerMessage::Pong { nonce: 7 },
+    ]
+    .into_iter()
+    .chain(phases.into_iter().map(ServerMessage::Phase))
+    .collect()
+}
+
+fn parse(text: &str) -> Value {
+    serde_json::from_str(text).unwrap()
+}
+
+#[test]
+fn every_client_message_round_trips() {
+    for message in client_messages() {
+        let text = message.encode();
+        assert_eq!(ClientMessage::decode(&text).unwrap(), message, "{text}");
+    }
+}
+
+#[test]
+fn every_server_message_round_trips() {
+    for message in server_messages() {
+        let text = message.encode();
+        assert_eq!(ServerMessage::decode(&text).unwrap(), message, "{text}");
+    }
+}
+
+#[test]
+fn join_is_tagged_with_its_type_and_target() {
+    let join = |target| ClientMessage::Join { version: 2, target }.encode();
+    assert_eq!(
+        parse(&join(Target::Queue)),
+        json!({"type": "join", "version": 2, "target": "queue"})
+    );
+    assert_eq!(
+        parse(&join(Target::Room("ab12".to_string()))),
+        json!({"type": "join", "version": 2, "target": {"room": "ab12"}})
+    );
+    assert_eq!(
+        parse(&join(Target::Rejoin {
+            room: "ab12".to_string(),
+            token: "secret".to_string(),
+        })),
+        json!({
+            "type": "join",
+            "version": 2,
+            "target": {"rejoin": {"room": "ab12", "token": "secret"}},
+        })
+    );
+}
+
+#[test]
+fn state_carries_the_snapshot_fields_next_to_its_type() {
+    let state = ServerMessage::State(Snapshot {
+        ball: Position::new(1.5, 2.0),
+        paddles: (Position::new(0.0, 10.0), Position::new(790.0, 20.0)),
+    });
+    assert_eq!(
+        parse(&state.encode()),
+        json!({
+            "type": "state",
+            "ball": {"x": 1.5, "y": 2.0},
+            "paddles": [{"x": 0.0, "y": 10.0}, {"x": 790.0, "y": 20.0}],
+        })
+    );
+}
+
+#[test]
+fn phase_is_tagged_with_both_its_type_and_phase() {
+    assert_eq!(
+        parse(
+            &ServerMessage::Phase(Phase::GameOver {
+                winner: Side::Right
+            })
+            .encode()
+        ),
+        json!({"type": "phase", "phase": "game_over", "winner": "right"})
+    );
+}
+
+#[test]
+fn malformed_client_messages_are_errors() {
+    let malformed = [
+        "",
+        " ",
+        "null",
+        "[]",
+        "{}",
+        "{\"type\"",
+        "{\"type\": \"join\"}",
+        "{\"type\": \"join\", \"version\": 2, \"target\": \"lobby\"}",
+        "{\"type\": \"join\", \"version\": -1, \"target\": \"queue\"}",
+        "{\"type\": \"input\", \"direction\": \"left\"}",
+        "{\"type\": \"ping\", \"nonce\": 1.5}",
+        "{\"type\": \"pong\", \"nonce\": 1}",
+        "{\"type\": \"JOIN\", \"version\": 2, \"target\": \"queue\"}",
+        "{\"direction\": \"up\"}",
+    ];
+    for text in malformed {
+        assert!(ClientMessage::decode(text).is_err(), "{text:?} decoded");
+    }
+}
+
+#[test]
+fn malformed_server_messages_are_errors() {
+    let malformed = [
+        "",
+        "{\"type\": \"state\", \"ball\": {\"x\": 1}}",
+        "{\"type\": \"phase\", \"phase\": \"overtime\"}",
+        "{\"type\": \"score\", \"left\": 1}",
+        "{\"type\": \"welcome\", \"version\": 2}",
+        "{\"type\": \"input\", \"direction\": \"up\"}",
+    ];
+    for text in malformed {
+        assert!(ServerMessage::decode(text).is_err(), "{text:?} decoded");
+    }
+}
+
+#[test]
+fn binary_input_is_an_error() {
+    let binary: Vec<u8> = (0..=255).collect();
+    let frames = [
+        String::from_utf8_lossy(&binary).into_owned(),
+        String::from_utf8_lossy(&[0x00, 0x01, 0x02, 0xff, 0xfe]).into_owned(),
+        "\0\0\0\0".to_string(),
+        "\u{feff}{\"type\": \"ping\", \"nonce\": 1}".to_string(),
+    ];
+    for text in &frames {
+        assert!(ClientMessage::decode(text).is_err(), "{text:?} decoded");
+        assert!(ServerMessage::decode(text).is_err(), "{text:?} decoded");
+    }
+}
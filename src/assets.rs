@@ -0,0 +1,302 @@
+use std::{collections::HashMap, fs, io, path::Path, sync::OnceLock};
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::precompress;
+
+// Hex digits of the content hash kept in fingerprinted names and ETags.
+const HASH_LENGTH: usize = 12;
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const REVALIDATE: &str = "no-cache";
+const RESOURCE_POLICY: &str = "same-origin";
+
+/// Content hashes of the files under /assets, taken once at startup.
+#[derive(Default)]
+pub struct Manifest {
+    /// `styles.css` to its hash.
+    hashes: HashMap<String, String>,
+    /// `styles.<hash>.css` back to `styles.css`.
+    originals: HashMap<String, String>,
+}
+
+impl Manifest {
+    #[cfg_attr(feature = "embed", allow(dead_code))]
+    pub fn build(dir: &Path) -> io::Result<Self> {
+        let mut manifest = Manifest::default();
+        manifest.walk(dir, "")?;
+        Ok(manifest)
+    }
+
+    #[cfg_attr(feature = "embed", allow(dead_code))]
+    fn walk(&mut self, dir: &Path, prefix: &str) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let name = format!("{prefix}{file_name}");
+            if entry.file_type()?.is_dir() {
+                self.walk(&entry.path(), &format!("{name}/"))?;
+                continue;
+            }
+            if precompress::is_encoded(&name) {
+                continue;
+            }
+            self.insert(name, &Sha256::digest(fs::read(entry.path())?));
+        }
+        Ok(())
+    }
+
+    /// Adds an asset by the SHA-256 digest of its content.
+    pub fn insert(&mut self, name: String, digest: &[u8]) {
+        let hash: String = digest
+            .iter()
+            .take(HASH_LENGTH / 2)
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        self.originals
+            .insert(fingerprint(&name, &hash), name.clone());
+        self.hashes.insert(name, hash);
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+}
+
+static MANIFEST: OnceLock<Manifest> = OnceLock::new();
+
+pub fn configure(manifest: Manifest) {
+    assert!(MANIFEST.set(manifest).is_ok(), "assets are configured once");
+}
+
+/// URL of an asset that changes whenever its content does, e.g.
+/// `/assets/styles.0123456789ab.css`. Unknown assets keep their plain URL.
+pub fn url(name: &str) -> String {
+    match MANIFEST
+        .get()
+        .and_then(|manifest| manifest.hashes.get(name))
+    {
+        Some(hash) => format!("/assets/{}", fingerprint(name, hash)),
+        None => format!("/assets/{name}"),
+    }
+}
+
+/// `pong_bg.wasm` becomes `pong_bg.<hash>.wasm`.
+fn fingerprint(name: &str, hash: &str) -> String {
+    let (dir, file) = match name.rfind('/') {
+        Some(slash) => name.split_at(slash + 1),
+        None => ("", name),
+    };
+    match file.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{dir}{stem}.{hash}.{extension}"),
+        _ => format!("{dir}{file}.{hash}"),
+    }
+}
+
+/// Marks responses from /assets, which are precompressed rather than
+/// compressed per request.
+#[derive(Clone, Copy)]
+pub struct Static;
+
+/// Serves fingerprinted paths as their original file, cached forever, and
+/// answers `If-None-Match` for plain paths so they revalidate cheaply.
+pub async fn cache(request: Request, next: Next) -> Response {
+    let mut response = serve(request, next).await;
+    response.extensions_mut().insert(Static);
+    // Lets cross-origin isolated pages load assets, and nobody else embed them.
+    response.headers_mut().insert(
+        HeaderName::from_static("cross-origin-resource-policy"),
+        HeaderValue::from_static(RESOURCE_POLICY),
+    );
+    response
+}
+
+async fn serve(mut request: Request, next: Next) -> Response {
+    let Some(manifest) = MANIFEST.get() else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path().trim_start_matches('/');
+
+    if let Some(original) = manifest.originals.get(path) {
+        *request.uri_mut() = Uri::try_from(format!("/{original}")).expect("asset names are paths");
+        let mut response = next.run(request).await;
+        if response.status().is_success() {
+            let headers = response.headers_mut();
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE));
+            headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        }
+        return response;
+    }
+
+    let Some(hash) = manifest.hashes.get(path) else {
+        return next.run(request).await;
+    };
+    // Weak, the compression layer may re-encode the body.
+    let etag = HeaderValue::try_from(format!("W/\"{hash}\"")).expect("hashes are hex");
+    if matches(request.headers(), hash) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, HeaderValue::from_static(REVALIDATE)),
+            ],
+        )
+            .into_response();
+    }
+    let mut response = next.run(request).await;
+    if response.status().is_success() {
+        let headers = response.headers_mut();
+        headers.insert(header::ETAG, etag);
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(REVALIDATE));
+        headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    response
+}
+
+/// Weak comparison of `If-None-Match` against the asset's hash.
+fn matches(headers: &HeaderMap, hash: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::{ServiceBuilder, ServiceExt};
+
+    use super::*;
+
+    const STYLES: &str = "body { margin: 0 }";
+
+    /// The manifest is global, every test shares this one.
+    fn manifest() -> &'static Manifest {
+        MANIFEST.get_or_init(|| {
+            let mut manifest = Manifest::default();
+            manifest.insert(String::from("styles.css"), &Sha256::digest(STYLES));
+            manifest.insert(String::from("js/app.js"), &Sha256::digest("app"));
+            manifest
+        })
+    }
+
+    fn hash(name: &str) -> &'static str {
+        &manifest().hashes[name]
+    }
+
+    /// Wrapped around the files like the /assets service, ahead of routing.
+    fn app() -> Router {
+        manifest();
+        let files = Router::new()
+            .route("/styles.css", get(|| async { STYLES }))
+            .route("/js/app.js", get(|| async { "app" }));
+        Router::new().fallback_service(
+            ServiceBuilder::new()
+                .layer(middleware::from_fn(cache))
+                .service(files),
+        )
+    }
+
+    async fn get_with(path: &str, if_none_match: Option<&str>) -> (Response, String) {
+        let mut request = Request::builder().uri(path);
+        if let Some(tags) = if_none_match {
+            request = request.header(header::IF_NONE_MATCH, tags);
+        }
+        let response = app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (
+            Response::from_parts(parts, Body::empty()),
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn urls_carry_the_content_hash() {
+        assert_eq!(hash("styles.css").len(), HASH_LENGTH);
+        assert_eq!(
+            url("styles.css"),
+            format!("/assets/styles.{}.css", hash("styles.css"))
+        );
+        assert_eq!(
+            url("js/app.js"),
+            format!("/assets/js/app.{}.js", hash("js/app.js"))
+        );
+        assert_eq!(url("unknown.js"), "/assets/unknown.js");
+    }
+
+    #[test]
+    fn fingerprints_go_before_the_last_extension() {
+        assert_eq!(fingerprint("pong_bg.wasm", "ab"), "pong_bg.ab.wasm");
+        assert_eq!(fingerprint("htmx.min.js", "ab"), "htmx.min.ab.js");
+        assert_eq!(fingerprint("js/app.js", "ab"), "js/app.ab.js");
+        assert_eq!(fingerprint("LICENSE", "ab"), "LICENSE.ab");
+        assert_eq!(fingerprint(".hidden", "ab"), ".hidden.ab");
+    }
+
+    #[tokio::test]
+    async fn fingerprinted_paths_serve_the_original_forever() {
+        let path = format!("/styles.{}.css", hash("styles.css"));
+        let (response, body) = get_with(&path, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body, STYLES);
+        let headers = response.headers();
+        assert_eq!(headers[header::CACHE_CONTROL], IMMUTABLE);
+        assert_eq!(headers[header::VARY], "accept-encoding");
+        assert_eq!(headers["cross-origin-resource-policy"], RESOURCE_POLICY);
+        assert!(response.extensions().get::<Static>().is_some());
+    }
+
+    #[tokio::test]
+    async fn stale_fingerprints_are_not_cached_forever() {
+        let (response, _) = get_with("/styles.000000000000.css", None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(!response.headers().contains_key(header::CACHE_CONTROL));
+    }
+
+    #[tokio::test]
+    async fn plain_paths_revalidate_with_a_weak_etag() {
+        let (response, body) = get_with("/styles.css", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body, STYLES);
+        let etag = format!("W/\"{}\"", hash("styles.css"));
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert_eq!(response.headers()[header::CACHE_CONTROL], REVALIDATE);
+    }
+
+    #[tokio::test]
+    async fn matching_etags_are_not_modified() {
+        let hash = hash("styles.css");
+        for tags in [
+            format!("W/\"{hash}\""),
+            format!("\"{hash}\""),
+            format!("\"other\", W/\"{hash}\""),
+            String::from("*"),
+        ] {
+            let (response, body) = get_with("/styles.css", Some(&tags)).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{tags}");
+            assert_eq!(body, "");
+            assert_eq!(
+                response.headers()[header::ETAG],
+                format!("W/\"{hash}\"").as_str()
+            );
+        }
+
+        let (response, body) = get_with("/styles.css", Some("W/\"other\"")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body, STYLES);
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use serde::Deserialize;
+use toml::value::Datetime;
+
+const FRONT_MATTER: &str = "+++";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    title: String,
+    date: Datetime,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Debug)]
+pub struct Post {
+    pub slug: String,
+    pub title: String,
+    pub date: Datetime,
+    pub tags: Vec<String>,
+    pub draft: bool,
+    pub body: String,
+}
+
+impl Post {
+    /// Parses a post made of `+++` delimited TOML front matter followed by markdown.
+    fn parse(slug: &str, text: &str) -> Result<Self, String> {
+        let text = text
+            .trim_start_matches('\u{feff}')
+            .strip_prefix(FRONT_MATTER)
+            .ok_or("missing +++ front matter")?;
+        let (front_matter, body) = text
+            .split_once(&format!("\n{FRONT_MATTER}"))
+            .ok_or("unterminated front matter")?;
+        let front_matter: FrontMatter =
+            toml::from_str(front_matter).map_err(|err| err.message().to_string())?;
+        if front_matter.date.date.is_none() {
+            return Err(String::from("date must include a calendar date"));
+        }
+        Ok(Post {
+            slug: slug.to_string(),
+            title: front_matter.title,
+            date: front_matter.date,
+            tags: front_matter.tags,
+            draft: front_matter.draft,
+            body: body.trim_start_matches(['\r', '\n']).to_string(),
+        })
+    }
+}
+
+/// Percent-encodes a slug or tag for use as a single path segment.
+pub fn path_segment(segment: &str) -> String {
+    askama::filters::urlencode_strict(segment).expect("percent-encoding cannot fail")
+}
+
+pub struct Listing {
+    pub posts: Vec<Arc<Post>>,
+    pub page: usize,
+    pub pages: usize,
+}
+
+pub struct Blog {
+    dir: PathBuf,
+    drafts: bool,
+    posts: RwLock<Arc<Vec<Arc<Post>>>>,
+}
+
+impl Blog {
+    pub fn load(dir: impl Into<PathBuf>, drafts: bool) -> Self {
+        let blog = Blog {
+            dir: dir.into(),
+            drafts,
+            posts: RwLock::default(),
+        };
+        match blog.reload() {
+            Ok(count) => tracing::info!("loaded {count} post(s) from {}", blog.dir.display()),
+            Err(err) => tracing::warn!("no blog posts loaded from {}: {err}", blog.dir.display()),
+        }
+        blog
+    }
+
+    /// Re-reads every post, keeping the current ones if the directory is unreadable.
+    pub fn reload(&self) -> io::Result<usize> {
+        let files = match read_dir(&self.dir) {
+            #[cfg(feature = "embed")]
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                tracing::info!("{} not found, using the embedded posts", self.dir.display());
+                crate::embed::posts()
+            }
+            files => files?,
+        };
+        let mut posts = Vec::new();
+        for (path, text) in files {
+            match text
+                .map_err(|err| err.to_string())
+                .and_then(|text| post(&path, &text))
+            {
+                Ok(post) if post.draft && !self.drafts => {}
+                Ok(post) => posts.push(Arc::new(post)),
+                Err(err) => tracing::warn!("skipping post {}: {err}", path.display()),
+            }
+        }
+        posts.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.slug.cmp(&b.slug)));
+
+        let count = posts.len();
+        *self.posts.write().unwrap() = Arc::new(posts);
+        Ok(count)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Published posts, newest first.
+    pub fn posts(&self) -> Arc<Vec<Arc<Post>>> {
+        Arc::clone(&self.posts.read().unwrap())
+    }
+
+    pub fn post(&self, slug: &str) -> Option<Arc<Post>> {
+        let posts = Arc::clone(&self.posts.read().unwrap());
+        posts.iter().find(|post| post.slug == slug).cloned()
+    }
+
+    /// Returns the 1-based `page` of posts, optionally limited to `tag`.
+    pub fn page(&self, tag: Option<&str>, page: usize, per_page: usize) -> Option<Listing> {
+        let posts = Arc::clone(&self.posts.read().unwrap());
+        let matching: Vec<_> = posts
+            .iter()
+            .filter(|post| tag.is_none_or(|tag| post.tags.iter().any(|t| t == tag)))
+            .collect();
+        if tag.is_some() && matching.is_empty() {
+            return None;
+        }
+
+        let pages = matching.len().div_ceil(per_page).max(1);
+        if page == 0 || page > pages {
+            return None;
+        }
+        Some(Listing {
+            posts: matching
+                .into_iter()
+                .skip((page - 1) * per_page)
+                .take(per_page)
+                .cloned()
+                .collect(),
+            page,
+            pages,
+        })
+    }
+}
+
+/// The markdown files in `dir` with their contents.
+fn read_dir(dir: &Path) -> io::Result<Vec<(PathBuf, io::Result<String>)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "md") {
+            let text = std::fs::read_to_string(&path);
+            files.push((path, text));
+        }
+    }
+    Ok(files)
+}
+
+fn post(path: &Path, text: &str) -> Result<Post, String> {
+    let slug = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("file name is not valid UTF-8")?;
+    Post::parse(slug, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(title: &str, date: &str, tags: &[&str], draft: bool) -> String {
+        format!(
+            "+++\ntitle = \"{title}\"\ndate = {date}\ntags = {tags:?}\ndraft = {draft}\n+++\n\nBody of {title}.\n"
+        )
+    }
+
+    fn post(slug: &str, date: &str, tags: &[&str]) -> Arc<Post> {
+        Arc::new(Post::parse(slug, &source(slug, date, tags, false)).unwrap())
+    }
+
+    fn blog(posts: Vec<Arc<Post>>) -> Blog {
+        Blog {
+            dir: PathBuf::new(),
+            drafts: false,
+            posts: RwLock::new(Arc::new(posts)),
+        }
+    }
+
+    fn slugs(listing: &Listing) -> Vec<&str> {
+        listing
+            .posts
+            .iter()
+            .map(|post| post.slug.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn front_matter_is_parsed_ahead_of_the_body() {
+        let text = format!("\u{feff}{}", source("Hello", "2024-02-01", &["rust"], true));
+        let post = Post::parse("hello", &text).unwrap();
+        assert_eq!(post.slug, "hello");
+        assert_eq!(post.title, "Hello");
+        assert_eq!(post.date.to_string(), "2024-02-01");
+        assert_eq!(post.tags, ["rust"]);
+        assert!(post.draft);
+        assert_eq!(post.body, "Body of Hello.\n");
+
+        let minimal = Post::parse("min", "+++\ntitle = \"Min\"\ndate = 2024-02-01\n+++\n").unwrap();
+        assert!(minimal.tags.is_empty() && !minimal.draft && minimal.body.is_empty());
+    }
+
+    #[test]
+    fn broken_front_matter_is_rejected() {
+        let error = |text: &str| Post::parse("broken", text).unwrap_err();
+        assert_eq!(error("title = \"No fence\"\n"), "missing +++ front matter");
+        assert_eq!(
+            error("+++\ntitle = \"Open\"\ndate = 2024-02-01\n"),
+            "unterminated front matter"
+        );
+        assert!(error("+++\ntitle = \"No date\"\n+++\n").contains("date"));
+        assert!(
+            error("+++\ntitle = \"Typo\"\ndate = 2024-02-01\ntagz = []\n+++\n").contains("tagz")
+        );
+        assert!(error("+++\ntitle = 1\ndate = 2024-02-01\n+++\n").contains("string"));
+        assert_eq!(
+            error("+++\ntitle = \"Time\"\ndate = 10:00:00\n+++\n"),
+            "date must include a calendar date"
+        );
+    }
+
+    #[test]
+    fn drafts_are_only_loaded_when_enabled() {
+        let dir = std::env::temp_dir().join(format!("blog-{}-drafts", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = [
+            (
+                "published.md",
+                source("Published", "2024-01-01", &[], false),
+            ),
+            ("draft.md", source("Draft", "2024-01-02", &[], true)),
+            ("broken.md", String::from("no front matter")),
+            ("notes.txt", source("Notes", "2024-01-03", &[], false)),
+        ];
+        for (name, text) in &files {
+            std::fs::write(dir.join(name), text).unwrap();
+        }
+
+        let published = Blog::load(&dir, false);
+        let with_drafts = Blog::load(&dir, true);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let slugs = |blog: &Blog| -> Vec<String> {
+            blog.posts().iter().map(|post| post.slug.clone()).collect()
+        };
+        assert_eq!(slugs(&published), ["published"]);
+        assert_eq!(slugs(&with_drafts), ["draft", "published"]);
+        assert!(published.post("draft").is_none());
+    }
+
+    // Embed builds fall back to the bundled posts instead.
+    #[cfg(not(feature = "embed"))]
+    #[test]
+    fn a_missing_directory_keeps_the_current_posts() {
+        let blog = blog(vec![post("kept", "2024-01-01", &[])]);
+        assert!(blog.reload().is_err());
+        assert_eq!(blog.posts().len(), 1);
+    }
+
+    #[test]
+    fn tags_filter_the_listing() {
+        let blog = blog(vec![
+            post("c", "2024-03-01", &["rust", "web"]),
+            post("b", "2024-02-01", &["web"]),
+            post("a", "2024-01-01", &["rust"]),
+        ]);
+        assert_eq!(slugs(&blog.page(Some("rust"), 1, 10).unwrap()), ["c", "a"]);
+        assert_eq!(slugs(&blog.page(None, 1, 10).unwrap()), ["c", "b", "a"]);
+        assert!(blog.page(Some("Rust"), 1, 10).is_none());
+        assert!(blog.page(Some("unknown"), 1, 10).is_none());
+    }
+
+    #[test]
+    fn pages_outside_the_listing_are_not_found() {
+        let blog = blog(vec![
+            post("c", "2024-03-01", &[]),
+            post("b", "2024-02-01", &[]),
+            post("a", "2024-01-01", &[]),
+        ]);
+        let last = blog.page(None, 2, 2).unwrap();
+        assert_eq!((last.page, last.pages), (2, 2));
+        assert_eq!(slugs(&last), ["a"]);
+        assert!(blog.page(None, 0, 2).is_none());
+        assert!(blog.page(None, 3, 2).is_none());
+
+        let exact = blog.page(None, 1, 3).unwrap();
+        assert_eq!(exact.pages, 1);
+        assert!(blog.page(None, 2, 3).is_none());
+    }
+
+    #[test]
+    fn an_empty_blog_has_one_empty_page() {
+        let blog = blog(Vec::new());
+        let listing = blog.page(None, 1, 10).unwrap();
+        assert!(listing.posts.is_empty());
+        assert_eq!((listing.page, listing.pages), (1, 1));
+        assert!(blog.page(None, 2, 10).is_none());
+    }
+
+    #[test]
+    fn path_segments_are_percent_encoded() {
+        assert_eq!(path_segment("rust"), "rust");
+        assert_eq!(path_segment("c# / f#?"), "c%23%20%2F%20f%23%3F");
+    }
+}
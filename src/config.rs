@@ -0,0 +1,655 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use axum::http::HeaderValue;
+use clap::{Parser, Subcommand};
+use pong_core::Settings;
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::{experiments, limits, matrix};
+
+const DEFAULT_CONFIG: &str = "mskasal.toml";
+const ENV_PREFIX: &str = "MSKASAL_";
+
+#[derive(Parser, Debug)]
+#[command(version, about = "mskasal.com web server")]
+pub struct Cli {
+    /// Path to the TOML config file [default: mskasal.toml when present]
+    #[arg(short, long, env = "MSKASAL_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Address to listen on, overrides `server.bind`
+    #[arg(long)]
+    bind: Option<SocketAddr>,
+
+    /// Directory served under /assets, overrides `server.assets_dir`
+    #[arg(long)]
+    assets_dir: Option<PathBuf>,
+
+    /// Tracing filter, overrides `log.filter`
+    #[arg(long)]
+    log_filter: Option<String>,
+
+    /// Override any config key, e.g. `--set matrix.default_size=20`
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Write .br, .zst and .gz siblings of the compressible assets and exit
+    Precompress,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub matrix: MatrixConfig,
+    pub blog: BlogConfig,
+    pub resume: ResumeConfig,
+    pub experiments: HashMap<String, ExperimentConfig>,
+    pub websocket: WebSocketConfig,
+    pub pong: PongConfig,
+    pub requests: RequestConfig,
+    pub security: SecurityConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    pub base_url: String,
+    pub assets_dir: PathBuf,
+    pub drain_timeout_secs: u64,
+    pub precompress: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatrixConfig {
+    pub default_size: u32,
+    pub max_size: u32,
+    pub snapshot: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlogConfig {
+    pub content_dir: PathBuf,
+    pub page_size: usize,
+    pub drafts: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResumeConfig {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExperimentConfig {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebSocketConfig {
+    pub max_connections: usize,
+    pub max_per_ip: usize,
+    pub messages_per_second: u32,
+    pub max_message_bytes: usize,
+    pub trusted_proxies: Vec<limits::TrustedProxy>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PongConfig {
+    pub points_to_win: u32,
+    pub serve_speed: f64,
+    pub speed_increment: f64,
+    pub max_speed: f64,
+    pub max_bounce_angle: f64,
+    pub spin: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestConfig {
+    pub timeout_secs: u64,
+    pub max_body_bytes: usize,
+    pub max_in_flight: usize,
+    pub catch_panics: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityConfig {
+    pub csp: bool,
+    pub hsts_max_age_secs: u64,
+    pub nosniff: bool,
+    pub referrer_policy: String,
+    pub permissions_policy: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub filter: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind: SocketAddr::from(([0, 0, 0, 0], 8080)),
+            base_url: String::from("https://mskasal.com"),
+            assets_dir: PathBuf::from("assets"),
+            drain_timeout_secs: 10,
+            precompress: true,
+        }
+    }
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        MatrixConfig {
+            default_size: matrix::DEFAULT_SIZE,
+            max_size: matrix::MAX_SIZE,
+            snapshot: PathBuf::from("data/matrix.json"),
+        }
+    }
+}
+
+impl Default for BlogConfig {
+    fn default() -> Self {
+        BlogConfig {
+            content_dir: PathBuf::from("content/blog"),
+            page_size: 10,
+            drafts: false,
+        }
+    }
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        ResumeConfig {
+            path: PathBuf::from("content/resume.toml"),
+        }
+    }
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig { enabled: true }
+    }
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        WebSocketConfig {
+            max_connections: 512,
+            max_per_ip: 8,
+            messages_per_second: 40,
+            max_message_bytes: 1024,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl Default for PongConfig {
+    fn default() -> Self {
+        let settings = Settings::default();
+        PongConfig {
+            points_to_win: settings.points_to_win,
+            serve_speed: settings.serve_speed,
+            speed_increment: settings.speed_increment,
+            max_speed: settings.max_speed,
+            max_bounce_angle: settings.max_bounce_angle,
+            spin: settings.spin,
+        }
+    }
+}
+
+impl PongConfig {
+    pub fn settings(&self) -> Settings {
+        Settings {
+            points_to_win: self.points_to_win,
+            serve_speed: self.serve_speed,
+            speed_increment: self.speed_increment,
+            max_speed: self.max_speed,
+            max_bounce_angle: self.max_bounce_angle,
+            spin: self.spin,
+        }
+    }
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        RequestConfig {
+            timeout_secs: 30,
+            max_body_bytes: 16 * 1024,
+            max_in_flight: 1024,
+            catch_panics: true,
+        }
+    }
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        SecurityConfig {
+            csp: true,
+            hsts_max_age_secs: 63_072_000,
+            nosniff: true,
+            referrer_policy: String::from("strict-origin-when-cross-origin"),
+            permissions_policy: String::from(
+                "camera=(), microphone=(), geolocation=(), payment=(), usb=()",
+            ),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            filter: String::from("mskasal=debug,tower_http=debug"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Override(String, String),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "cannot read {}: {err}", path.display()),
+            ConfigError::Parse(path, err) => write!(f, "invalid config {}: {err}", path.display()),
+            ConfigError::Override(source, reason) => {
+                write!(f, "invalid override {source}: {reason}")
+            }
+            ConfigError::Invalid(reason) => write!(f, "invalid config: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Layers defaults, the config file, `MSKASAL_*` environment variables
+    /// and command line flags, in that order.
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        let table = match &cli.config {
+            Some(path) => read_table(path)?,
+            None if Path::new(DEFAULT_CONFIG).exists() => read_table(Path::new(DEFAULT_CONFIG))?,
+            None => Table::new(),
+        };
+        Self::from_layers(table, std::env::vars(), cli)
+    }
+
+    /// Applies the environment, then the flags, over the config file's `table`.
+    fn from_layers(
+        mut table: Table,
+        env: impl IntoIterator<Item = (String, String)>,
+        cli: &Cli,
+    ) -> Result<Self, ConfigError> {
+        let mut rust_log = None;
+        for (name, value) in env {
+            if name == "RUST_LOG" {
+                rust_log = Some(value);
+                continue;
+            }
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            if key == "CONFIG" {
+                continue;
+            }
+            let path = key.to_lowercase().replace("__", ".");
+            set(&mut table, &path, &value).map_err(|reason| ConfigError::Override(name, reason))?;
+        }
+        if let Some(filter) = rust_log {
+            set(&mut table, "log.filter", &filter)
+                .map_err(|reason| ConfigError::Override(String::from("RUST_LOG"), reason))?;
+        }
+
+        let flags = [
+            ("server.bind", cli.bind.map(|bind| bind.to_string())),
+            (
+                "server.assets_dir",
+                cli.assets_dir.as_ref().map(|dir| dir.display().to_string()),
+            ),
+            ("log.filter", cli.log_filter.clone()),
+        ];
+        for (path, value) in flags {
+            if let Some(value) = value {
+                set(&mut table, path, &value)
+                    .map_err(|reason| ConfigError::Override(format!("--{path}"), reason))?;
+            }
+        }
+        for assignment in &cli.overrides {
+            let source = format!("--set {assignment}");
+            let (path, value) = assignment.split_once('=').ok_or_else(|| {
+                ConfigError::Override(source.clone(), String::from("expected KEY=VALUE"))
+            })?;
+            set(&mut table, path.trim(), value.trim())
+                .map_err(|reason| ConfigError::Override(source, reason))?;
+        }
+
+        let mut config: Config = table
+            .try_into()
+            .map_err(|err| ConfigError::Invalid(format!("{err}")))?;
+        config.server.base_url = config.server.base_url.trim_end_matches('/').to_string();
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let base_url = &self.server.base_url;
+        if !(base_url.starts_with("https://") || base_url.starts_with("http://")) {
+            return Err(ConfigError::Invalid(format!(
+                "server.base_url {base_url} must start with http:// or https://"
+            )));
+        }
+        if cfg!(not(feature = "embed")) && !self.server.assets_dir.is_dir() {
+            return Err(ConfigError::Invalid(format!(
+                "server.assets_dir {} is not a directory",
+                self.server.assets_dir.display()
+            )));
+        }
+        if !(1..=matrix::MAX_SIZE).contains(&self.matrix.max_size) {
+            return Err(ConfigError::Invalid(format!(
+                "matrix.max_size must be between 1 and {}",
+                matrix::MAX_SIZE
+            )));
+        }
+        if !(1..=self.matrix.max_size).contains(&self.matrix.default_size) {
+            return Err(ConfigError::Invalid(format!(
+                "matrix.default_size must be between 1 and matrix.max_size ({})",
+                self.matrix.max_size
+            )));
+        }
+        if let Some(slug) = self
+            .experiments
+            .keys()
+            .find(|slug| !experiments::REGISTRY.iter().any(|e| e.slug == *slug))
+        {
+            return Err(ConfigError::Invalid(format!("unknown experiment `{slug}`")));
+        }
+        let websocket = &self.websocket;
+        if websocket.max_connections == 0
+            || websocket.max_per_ip == 0
+            || websocket.messages_per_second == 0
+            || websocket.max_message_bytes == 0
+        {
+            return Err(ConfigError::Invalid(String::from(
+                "websocket limits must all be at least 1",
+            )));
+        }
+        if websocket.max_message_bytes > limits::MAX_MESSAGE_BYTES {
+            return Err(ConfigError::Invalid(format!(
+                "websocket.max_message_bytes must be at most {}",
+                limits::MAX_MESSAGE_BYTES
+            )));
+        }
+        if let Err(reason) = self.pong.settings().validate() {
+            return Err(ConfigError::Invalid(format!("pong.{reason}")));
+        }
+        if self.requests.max_body_bytes == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "requests.max_body_bytes must be at least 1",
+            )));
+        }
+        for (key, value) in [
+            ("security.referrer_policy", &self.security.referrer_policy),
+            (
+                "security.permissions_policy",
+                &self.security.permissions_policy,
+            ),
+        ] {
+            if HeaderValue::from_str(value).is_err() {
+                return Err(ConfigError::Invalid(format!(
+                    "{key} is not a valid header value"
+                )));
+            }
+        }
+        if self.blog.page_size == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "blog.page_size must be at least 1",
+            )));
+        }
+        if let Err(err) = tracing_subscriber::EnvFilter::try_new(&self.log.filter) {
+            return Err(ConfigError::Invalid(format!("log.filter: {err}")));
+        }
+        Ok(())
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table, ConfigError> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.to_path_buf(), err))?;
+    text.parse()
+        .map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+}
+
+/// Sets a dotted `path` in `table`, reading `raw` as a TOML value and
+/// falling back to a plain string so `0.0.0.0:8080` needs no quoting.
+fn set(table: &mut Table, path: &str, raw: &str) -> Result<(), String> {
+    let value = format!("value = {raw}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut parsed| parsed.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()));
+
+    let mut keys = path.split('.').peekable();
+    let mut current = table;
+    while let Some(key) = keys.next() {
+        if key.is_empty() {
+            return Err(format!("empty key in `{path}`"));
+        }
+        if keys.peek().is_none() {
+            current.insert(key.to_string(), value);
+            return Ok(());
+        }
+        current = match current
+            .entry(key)
+            .or_insert_with(|| Value::Table(Table::new()))
+        {
+            Value::Table(table) => table,
+            _ => return Err(format!("`{key}` is not a table")),
+        };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("mskasal").chain(args.iter().copied())).unwrap()
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn file(text: &str) -> Table {
+        text.parse().unwrap()
+    }
+
+    fn load(file_text: &str, vars: &[(&str, &str)], args: &[&str]) -> Result<Config, ConfigError> {
+        Config::from_layers(file(file_text), env(vars), &cli(args))
+    }
+
+    fn error(result: Result<Config, ConfigError>) -> String {
+        result.expect_err("config should be rejected").to_string()
+    }
+
+    #[test]
+    fn defaults_apply_without_any_layer() {
+        let config = load("", &[], &[]).unwrap();
+        assert_eq!(config.matrix.default_size, matrix::DEFAULT_SIZE);
+        assert_eq!(config.server.bind, SocketAddr::from(([0, 0, 0, 0], 8080)));
+    }
+
+    #[test]
+    fn env_overrides_the_file_and_flags_override_env() {
+        let config = load(
+            "[matrix]\ndefault_size = 10\nmax_size = 60\n[blog]\npage_size = 5\n",
+            &[
+                ("MSKASAL_MATRIX__DEFAULT_SIZE", "20"),
+                ("MSKASAL_MATRIX__MAX_SIZE", "50"),
+                ("MSKASAL_SERVER__BIND", "127.0.0.1:9000"),
+            ],
+            &[
+                "--set",
+                "matrix.default_size=30",
+                "--bind",
+                "127.0.0.1:9001",
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.blog.page_size, 5);
+        assert_eq!(config.matrix.max_size, 50);
+        assert_eq!(config.matrix.default_size, 30);
+        assert_eq!(config.server.bind, SocketAddr::from(([127, 0, 0, 1], 9001)));
+    }
+
+    #[test]
+    fn set_overrides_the_named_flags() {
+        let config = load(
+            "",
+            &[],
+            &[
+                "--bind",
+                "127.0.0.1:9001",
+                "--set",
+                "server.bind=127.0.0.1:9002",
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.server.bind, SocketAddr::from(([127, 0, 0, 1], 9002)));
+    }
+
+    #[test]
+    fn rust_log_sits_between_the_file_and_the_log_filter_flag() {
+        let file_text = "[log]\nfilter = \"info\"\n";
+        let rust_log = [("RUST_LOG", "warn")];
+        assert_eq!(load(file_text, &[], &[]).unwrap().log.filter, "info");
+        assert_eq!(load(file_text, &rust_log, &[]).unwrap().log.filter, "warn");
+        let config = load(file_text, &rust_log, &["--log-filter", "error"]).unwrap();
+        assert_eq!(config.log.filter, "error");
+    }
+
+    #[test]
+    fn unrelated_and_config_variables_are_not_keys() {
+        let config = load(
+            "",
+            &[("MSKASAL_CONFIG", "other.toml"), ("HOME", "/root")],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(config.matrix.default_size, matrix::DEFAULT_SIZE);
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_are_rejected() {
+        let message = error(load("", &[("MSKASAL_MATRIX__DEFAULT_SIZE", "big")], &[]));
+        assert!(message.contains("default_size"), "{message}");
+
+        let message = error(load("", &[], &["--set", "blog.drafts=maybe"]));
+        assert!(message.contains("drafts"), "{message}");
+
+        let message = error(load("[server]\nbind = 8080\n", &[], &[]));
+        assert!(message.contains("bind"), "{message}");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let message = error(load("", &[("MSKASAL_MATRIX__DEFAULT_SZE", "20")], &[]));
+        assert!(message.contains("default_sze"), "{message}");
+
+        let message = error(load("", &[("MSKASAL_MATRX__MAX_SIZE", "20")], &[]));
+        assert!(message.contains("matrx"), "{message}");
+
+        let message = error(load("", &[], &["--set", "server.bnd=127.0.0.1:1"]));
+        assert!(message.contains("bnd"), "{message}");
+    }
+
+    #[test]
+    fn malformed_overrides_are_rejected() {
+        let message = error(load("", &[], &["--set", "matrix.default_size"]));
+        assert!(message.contains("expected KEY=VALUE"), "{message}");
+
+        let message = error(load("", &[], &["--set", "matrix..max_size=1"]));
+        assert!(message.contains("empty key"), "{message}");
+
+        let message = error(load(
+            "[server]\nbind = \"127.0.0.1:8080\"\n",
+            &[("MSKASAL_SERVER__BIND__PORT", "1")],
+            &[],
+        ));
+        assert!(
+            message.starts_with("invalid override MSKASAL_SERVER__BIND__PORT"),
+            "{message}"
+        );
+        assert!(message.contains("`bind` is not a table"), "{message}");
+    }
+
+    #[test]
+    fn layered_values_are_validated() {
+        let message = error(load("", &[("MSKASAL_MATRIX__DEFAULT_SIZE", "0")], &[]));
+        assert!(message.contains("matrix.default_size"), "{message}");
+
+        let message = error(load(
+            "",
+            &[],
+            &["--set", "websocket.max_message_bytes=16777217"],
+        ));
+        assert!(message.contains("websocket.max_message_bytes"), "{message}");
+    }
+
+    #[test]
+    fn trusted_proxies_are_parsed_from_any_layer() {
+        let config = load(
+            "[websocket]\ntrusted_proxies = [\"10.0.0.0/8\"]\n",
+            &[],
+            &[
+                "--set",
+                "websocket.trusted_proxies=[\"10.0.0.0/8\", \"2001:db8::1\"]",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            config.websocket.trusted_proxies,
+            [
+                "10.0.0.0/8".parse().unwrap(),
+                "2001:db8::1".parse().unwrap()
+            ]
+        );
+
+        let message = error(load(
+            "",
+            &[("MSKASAL_WEBSOCKET__TRUSTED_PROXIES", "[\"10.0.0.0/40\"]")],
+            &[],
+        ));
+        assert!(message.contains("10.0.0.0/40"), "{message}");
+    }
+}
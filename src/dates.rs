@@ -0,0 +1,196 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use toml::value::{Date, Datetime, Offset, Time};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+// 1970-01-01 was a Thursday.
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+pub fn from_system_time(time: SystemTime) -> Datetime {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let seconds = seconds % 86_400;
+    Datetime {
+        date: Some(Date { year, month, day }),
+        time: Some(Time {
+            hour: (seconds / 3_600) as u8,
+            minute: (seconds % 3_600 / 60) as u8,
+            second: (seconds % 60) as u8,
+            nanosecond: 0,
+        }),
+        offset: Some(Offset::Z),
+    }
+}
+
+/// `2024-01-31T09:30:00Z`, as Atom expects. Missing times are midnight UTC.
+pub fn rfc3339(datetime: &Datetime) -> String {
+    let (date, time) = parts(datetime);
+    let offset = match datetime.offset {
+        Some(Offset::Custom { minutes }) => {
+            let sign = if minutes < 0 { '-' } else { '+' };
+            let minutes = minutes.unsigned_abs();
+            format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+        }
+        _ => String::from("Z"),
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{offset}",
+        date.year, date.month, date.day, time.hour, time.minute, time.second
+    )
+}
+
+/// `Wed, 31 Jan 2024 09:30:00 +0000`, as RSS expects.
+pub fn rfc2822(datetime: &Datetime) -> String {
+    let (date, time) = parts(datetime);
+    let weekday = days_from_civil(date).rem_euclid(7) as usize;
+    let minutes = match datetime.offset {
+        Some(Offset::Custom { minutes }) => minutes,
+        _ => 0,
+    };
+    let sign = if minutes < 0 { '-' } else { '+' };
+    let offset = minutes.unsigned_abs();
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} {sign}{:02}{:02}",
+        WEEKDAYS[weekday],
+        date.day,
+        MONTHS[usize::from(date.month.clamp(1, 12)) - 1],
+        date.year,
+        time.hour,
+        time.minute,
+        time.second,
+        offset / 60,
+        offset % 60
+    )
+}
+
+/// `2024-01-31`, as sitemaps expect.
+pub fn w3c_date(datetime: &Datetime) -> String {
+    let (date, _) = parts(datetime);
+    format!("{:04}-{:02}-{:02}", date.year, date.month, date.day)
+}
+
+fn parts(datetime: &Datetime) -> (Date, Time) {
+    let date = datetime.date.unwrap_or(Date {
+        year: 1970,
+        month: 1,
+        day: 1,
+    });
+    let time = datetime.time.unwrap_or(Time {
+        hour: 0,
+        minute: 0,
+        second: 0,
+        nanosecond: 0,
+    });
+    (date, time)
+}
+
+// Howard Hinnant's civil calendar algorithms.
+fn days_from_civil(date: Date) -> i64 {
+    let year = i64::from(date.year) - i64::from(date.month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(date.month);
+    let day_of_year =
+        (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(date.day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (u16, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as u16, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn at(seconds: u64) -> Datetime {
+        from_system_time(UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    fn weekday(datetime: &Datetime) -> &'static str {
+        WEEKDAYS[days_from_civil(parts(datetime).0).rem_euclid(7) as usize]
+    }
+
+    #[test]
+    fn the_epoch() {
+        let epoch = at(0);
+        assert_eq!(rfc3339(&epoch), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc2822(&epoch), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(w3c_date(&epoch), "1970-01-01");
+        assert_eq!(weekday(&epoch), "Thu");
+    }
+
+    #[test]
+    fn a_leap_day_and_the_day_after() {
+        let leap_day = at(1_709_209_845);
+        assert_eq!(rfc3339(&leap_day), "2024-02-29T12:30:45Z");
+        assert_eq!(rfc2822(&leap_day), "Thu, 29 Feb 2024 12:30:45 +0000");
+        assert_eq!(weekday(&leap_day), "Thu");
+
+        let next = at(1_709_251_200);
+        assert_eq!(rfc3339(&next), "2024-03-01T00:00:00Z");
+        assert_eq!(rfc2822(&next), "Fri, 01 Mar 2024 00:00:00 +0000");
+        assert_eq!(weekday(&next), "Fri");
+    }
+
+    #[test]
+    fn a_year_boundary() {
+        let last = at(946_684_799);
+        assert_eq!(rfc3339(&last), "1999-12-31T23:59:59Z");
+        assert_eq!(rfc2822(&last), "Fri, 31 Dec 1999 23:59:59 +0000");
+        assert_eq!(weekday(&last), "Fri");
+
+        let first = at(946_684_800);
+        assert_eq!(rfc3339(&first), "2000-01-01T00:00:00Z");
+        assert_eq!(rfc2822(&first), "Sat, 01 Jan 2000 00:00:00 +0000");
+        assert_eq!(weekday(&first), "Sat");
+    }
+
+    #[test]
+    fn a_pre_2000_date_with_an_offset() {
+        let datetime: Datetime = "1985-10-26T01:21:00-07:00".parse().unwrap();
+        assert_eq!(rfc3339(&datetime), "1985-10-26T01:21:00-07:00");
+        assert_eq!(rfc2822(&datetime), "Sat, 26 Oct 1985 01:21:00 -0700");
+        assert_eq!(w3c_date(&datetime), "1985-10-26");
+        assert_eq!(weekday(&datetime), "Sat");
+    }
+
+    #[test]
+    fn dates_without_a_time_are_midnight_utc() {
+        let datetime: Datetime = "2024-01-31".parse().unwrap();
+        assert_eq!(rfc3339(&datetime), "2024-01-31T00:00:00Z");
+        assert_eq!(rfc2822(&datetime), "Wed, 31 Jan 2024 00:00:00 +0000");
+    }
+
+    #[test]
+    fn civil_days_round_trip() {
+        // 1900-01-01 to 2100-12-31, across both century rules.
+        for days in -25_567..=47_846 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(Date { year, month, day }), days);
+        }
+        assert_eq!(civil_from_days(-25_567), (1900, 1, 1));
+        assert_eq!(civil_from_days(47_846), (2100, 12, 31));
+    }
+}
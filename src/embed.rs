@@ -0,0 +1,261 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    sync::OnceLock,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Bytes,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+
+use crate::{
+    assets::Manifest,
+    page::ErrorPage,
+    precompress::{self, Encoding, Summary},
+};
+
+/// The assets directory, compiled into release builds. Debug builds read it
+/// from the source tree instead.
+#[derive(RustEmbed)]
+#[folder = "assets/"]
+#[exclude = "*.br"]
+#[exclude = "*.zst"]
+#[exclude = "*.gz"]
+struct Embedded;
+
+/// The résumé and blog posts under `content/`, used when the configured
+/// files are missing so the binary runs from any directory.
+#[derive(RustEmbed)]
+#[folder = "content/"]
+#[include = "resume.toml"]
+#[include = "blog/*.md"]
+struct Content;
+
+// Compressed copies of the embedded assets, in `Encoding::ALL` order.
+static COMPRESSED: OnceLock<HashMap<String, Vec<(Encoding, Bytes)>>> = OnceLock::new();
+
+pub fn manifest() -> Manifest {
+    let mut manifest = Manifest::default();
+    for name in Embedded::iter() {
+        if let Some(file) = Embedded::get(&name) {
+            manifest.insert(name.into_owned(), &file.metadata.sha256_hash());
+        }
+    }
+    manifest
+}
+
+/// Compresses the embedded assets in memory, where the disk build writes siblings.
+pub fn precompress() -> Summary {
+    let mut compressed = HashMap::new();
+    for name in Embedded::iter() {
+        let Some(file) = Embedded::get(&name) else {
+            continue;
+        };
+        if !precompress::compressible(&name, file.data.len() as u64) {
+            continue;
+        }
+        let encoded: Vec<_> = Encoding::ALL
+            .into_iter()
+            .filter_map(|encoding| {
+                let data = encoding.compress(&file.data).ok()?;
+                Some((encoding, Bytes::from(data)))
+            })
+            .collect();
+        compressed.insert(name.into_owned(), encoded);
+    }
+    let written = compressed.values().map(Vec::len).sum();
+    let _ = COMPRESSED.set(compressed);
+    Summary { written, fresh: 0 }
+}
+
+/// The bundled `content/resume.toml`.
+pub fn resume() -> io::Result<String> {
+    content("resume.toml")
+}
+
+/// The bundled `content/blog` posts as `(path, text)` pairs.
+pub fn posts() -> Vec<(PathBuf, io::Result<String>)> {
+    Content::iter()
+        .filter(|name| name.starts_with("blog/"))
+        .map(|name| (PathBuf::from(name.as_ref()), content(&name)))
+        .collect()
+}
+
+fn content(name: &str) -> io::Result<String> {
+    let file = Content::get(name).ok_or(io::ErrorKind::NotFound)?;
+    String::from_utf8(file.data.into_owned())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Serves the embedded asset named by the request path.
+pub async fn serve(request: Request) -> Response {
+    let name = request.uri().path().trim_start_matches('/');
+    respond(name, request.headers())
+}
+
+pub async fn favicon(headers: HeaderMap) -> Response {
+    respond("favicon.ico", &headers)
+}
+
+fn respond(name: &str, request: &HeaderMap) -> Response {
+    let Some(file) = Embedded::get(name) else {
+        return ErrorPage(StatusCode::NOT_FOUND).into_response();
+    };
+    let mut headers = HeaderMap::new();
+    if let Ok(mime) = HeaderValue::try_from(file.metadata.mimetype()) {
+        headers.insert(header::CONTENT_TYPE, mime);
+    }
+    if let Some(seconds) = file.metadata.last_modified() {
+        let modified = httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(seconds));
+        if let Ok(modified) = HeaderValue::try_from(modified) {
+            headers.insert(header::LAST_MODIFIED, modified);
+        }
+    }
+
+    let encoded = COMPRESSED
+        .get()
+        .and_then(|compressed| compressed.get(name))
+        .and_then(|encoded| {
+            encoded
+                .iter()
+                .find(|(encoding, _)| accepts(request, encoding.token()))
+        });
+    let body = match (encoded, file.data) {
+        (Some((encoding, data)), _) => {
+            headers.insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.token()),
+            );
+            data.clone()
+        }
+        (None, Cow::Borrowed(data)) => Bytes::from_static(data),
+        (None, Cow::Owned(data)) => Bytes::from(data),
+    };
+    (headers, body).into_response()
+}
+
+/// Whether `Accept-Encoding` allows `token`, ignoring preferences beyond `q=0`.
+/// Naming the coding outranks `*`, so `br;q=0, *` still refuses brotli.
+fn accepts(headers: &HeaderMap, token: &str) -> bool {
+    let mut named = None;
+    let mut wildcard = None;
+    let codings = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for coding in codings {
+        let mut parts = coding.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let refused = parts.any(|param| {
+            param.split_once('=').is_some_and(|(key, q)| {
+                key.trim().eq_ignore_ascii_case("q")
+                    && q.trim().parse::<f32>().is_ok_and(|q| q == 0.0)
+            })
+        });
+        if name.eq_ignore_ascii_case(token) {
+            named = Some(!refused);
+        } else if name == "*" {
+            wildcard = Some(!refused);
+        }
+    }
+    named.or(wildcard).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept_encoding(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::try_from(value).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn listed_codings_are_accepted() {
+        let headers = accept_encoding("gzip, deflate, br;q=0.8");
+        assert!(accepts(&headers, "br"));
+        assert!(accepts(&headers, "gzip"));
+        assert!(!accepts(&headers, "zstd"));
+        assert!(!accepts(&HeaderMap::new(), "gzip"));
+        assert!(!accepts(&accept_encoding("identity"), "gzip"));
+    }
+
+    #[test]
+    fn codings_match_in_any_case() {
+        assert!(accepts(&accept_encoding("GZIP, Br"), "gzip"));
+        assert!(accepts(&accept_encoding("GZIP, Br"), "br"));
+    }
+
+    #[test]
+    fn a_zero_weight_refuses_the_coding() {
+        for value in [
+            "br;q=0",
+            "br; q=0.0",
+            "br;Q=0",
+            "br ; q=0.000",
+            "gzip, br;q=0",
+        ] {
+            assert!(!accepts(&accept_encoding(value), "br"), "{value}");
+        }
+        assert!(accepts(&accept_encoding("br;q=0.001"), "br"));
+    }
+
+    #[test]
+    fn a_wildcard_covers_codings_not_named() {
+        assert!(accepts(&accept_encoding("*"), "zstd"));
+        assert!(!accepts(&accept_encoding("*;q=0"), "zstd"));
+        assert!(accepts(&accept_encoding("*;q=0, br"), "br"));
+        assert!(!accepts(&accept_encoding("br;q=0, *"), "br"));
+        assert!(accepts(&accept_encoding("br;q=0, *"), "gzip"));
+    }
+
+    #[test]
+    fn codings_can_span_several_headers() {
+        let mut headers = accept_encoding("gzip");
+        headers.append(header::ACCEPT_ENCODING, HeaderValue::from_static("br"));
+        assert!(accepts(&headers, "br"));
+    }
+
+    #[tokio::test]
+    async fn embedded_assets_are_served_with_their_type_and_date() {
+        let response = respond("styles.css", &HeaderMap::new());
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_TYPE], "text/css");
+        let modified = headers[header::LAST_MODIFIED].to_str().unwrap();
+        assert!(httpdate::parse_http_date(modified).is_ok(), "{modified}");
+        assert!(!headers.contains_key(header::CONTENT_ENCODING));
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, Embedded::get("styles.css").unwrap().data.as_ref());
+    }
+
+    #[test]
+    fn missing_assets_are_not_found() {
+        let response = respond("no-such-file.js", &HeaderMap::new());
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn the_content_is_bundled() {
+        assert!(resume().unwrap().contains("[basics]"));
+        assert!(!posts().is_empty());
+        assert!(posts()
+            .iter()
+            .all(|(path, text)| path.starts_with("blog") && text.is_ok()));
+    }
+}
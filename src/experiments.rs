@@ -0,0 +1,339 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use askama::Template;
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use crate::{
+    config::ExperimentConfig,
+    matrix::Cell,
+    page::{self, ErrorPage, Htmx, Page, HTMX_VARY},
+    security, AppState,
+};
+
+pub struct Experiment {
+    pub slug: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// wasm-pack module served from /assets, e.g. `pong` for /assets/pong.js.
+    pub wasm: Option<&'static str>,
+    /// Inline experiments are fragments swapped into /experiments, the rest are full pages.
+    pub inline: bool,
+    /// Needs cross-origin isolation, e.g. for wasm threads. The page is sent
+    /// with COOP/COEP and always opened on its own rather than swapped in.
+    pub isolated: bool,
+    /// Label of the button or link that opens the experiment.
+    pub action: &'static str,
+    pub enabled: bool,
+    pub page: fn(&AppState, &'static Experiment, Htmx) -> Response,
+    /// Routes the experiment needs besides its page.
+    pub routes: fn(Router<AppState>) -> Router<AppState>,
+}
+
+impl Experiment {
+    pub fn path(&self) -> String {
+        format!("/{}", self.slug)
+    }
+
+    /// Fingerprinted URL of the wasm-pack JS glue for the experiment's module.
+    pub fn module(&self) -> String {
+        crate::assets::url(&format!("{}.js", self.wasm.unwrap_or(self.slug)))
+    }
+
+    /// Responds with the experiment's page, isolated when it asks to be.
+    pub fn respond(&'static self, state: &AppState, htmx: Htmx) -> Response {
+        let mut response = (self.page)(state, self, htmx);
+        if self.isolated {
+            security::isolate(&mut response);
+        }
+        response
+    }
+
+    /// Whether /experiments swaps the experiment in. An isolated one needs a
+    /// document of its own, so it gets a link to its page instead.
+    pub fn swapped_in(&self) -> bool {
+        self.inline && !self.isolated
+    }
+
+    /// Where navigation should point, inline experiments live on /experiments.
+    pub fn href(&self) -> String {
+        if self.swapped_in() {
+            format!("/experiments#{}", self.slug)
+        } else {
+            self.path()
+        }
+    }
+}
+
+pub static REGISTRY: [Experiment; 4] = [
+    Experiment {
+        slug: "pong",
+        title: "Pong",
+        description: "Two player Pong against whoever else is online, played over a websocket.",
+        wasm: Some("pong"),
+        inline: true,
+        isolated: false,
+        action: "Play!",
+        enabled: true,
+        page: |_, experiment, htmx| inline(PongTemplate { experiment }, experiment, htmx),
+        routes: |router| router.route("/ws", get(crate::ws_handler)),
+    },
+    Experiment {
+        slug: "ocr",
+        title: "OCR",
+        description: "Draw a character in the canvas and see what it reads as.",
+        wasm: Some("ocr"),
+        inline: true,
+        isolated: false,
+        action: "Try!",
+        enabled: true,
+        page: |_, experiment, htmx| inline(OcrTemplate { experiment }, experiment, htmx),
+        routes: |router| router,
+    },
+    Experiment {
+        slug: "led_matrix",
+        title: "Led Matrix",
+        description: "A led matrix display drawn from WebAssembly.",
+        wasm: Some("led_matrix"),
+        inline: true,
+        isolated: false,
+        action: "Try!",
+        enabled: true,
+        page: |_, experiment, htmx| inline(LedMatrixTemplate { experiment }, experiment, htmx),
+        routes: |router| router,
+    },
+    Experiment {
+        slug: "dyn_matrix",
+        title: "Dynamic Matrix",
+        description: "A shared board anyone can flip cells on, synced live to every viewer.",
+        wasm: None,
+        inline: false,
+        isolated: false,
+        action: "Try!",
+        enabled: true,
+        page: |state, _, _| {
+            let (last_event_id, cells) = state.matrix.rows();
+            Page(DynMatrixTemplate {
+                size: cells.len() as u32,
+                cells,
+                last_event_id,
+            })
+            .into_response()
+        },
+        routes: |router| {
+            router
+                .route("/matrix/size", get(crate::matrix_size_handler))
+                .route("/matrix/events", get(crate::matrix_events_handler))
+                .route("/matrix/:i/:j", get(crate::matrix_state_handler))
+        },
+    },
+];
+
+static ENABLED: OnceLock<Vec<&'static Experiment>> = OnceLock::new();
+
+/// Applies the `[experiments.<slug>]` config tables to the registry, once at startup.
+pub fn configure(config: &HashMap<String, ExperimentConfig>) {
+    let enabled = REGISTRY
+        .iter()
+        .filter(|experiment| {
+            config
+                .get(experiment.slug)
+                .map_or(experiment.enabled, |config| config.enabled)
+        })
+        .collect();
+    assert!(
+        ENABLED.set(enabled).is_ok(),
+        "experiments are configured once"
+    );
+}
+
+pub fn enabled() -> &'static [&'static Experiment] {
+    ENABLED.get().map_or(&[], Vec::as_slice)
+}
+
+/// Routes each experiment's page, isolated ones with COOP/COEP, and the routes
+/// it needs besides.
+pub fn routes(router: Router<AppState>, experiments: &[&'static Experiment]) -> Router<AppState> {
+    experiments.iter().fold(router, |router, &experiment| {
+        let page = get(
+            move |State(state): State<AppState>, htmx: Htmx| async move {
+                experiment.respond(&state, htmx)
+            },
+        );
+        (experiment.routes)(router.route(&experiment.path(), page))
+    })
+}
+
+/// The fragment itself for htmx swaps, wrapped in the base layout otherwise so
+/// the experiment's URL works on its own. Isolated pages are linked, not
+/// swapped in, so they always get the layout.
+fn inline<T: Template>(fragment: T, experiment: &'static Experiment, htmx: Htmx) -> Response {
+    let response = if htmx.wants_fragment() && experiment.swapped_in() {
+        Page(fragment).into_response()
+    } else {
+        match page::render(&fragment) {
+            Ok(fragment) => Page(ExperimentTemplate {
+                experiment,
+                fragment,
+            })
+            .into_response(),
+            Err(_) => ErrorPage(StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        }
+    };
+    ([(header::VARY, HTMX_VARY)], response).into_response()
+}
+
+#[derive(Template)]
+#[template(path = "pong.html")]
+pub struct PongTemplate {
+    experiment: &'static Experiment,
+}
+
+#[derive(Template)]
+#[template(path = "ocr.html")]
+pub struct OcrTemplate {
+    experiment: &'static Experiment,
+}
+
+#[derive(Template)]
+#[template(path = "led_matrix.html")]
+pub struct LedMatrixTemplate {
+    experiment: &'static Experiment,
+}
+
+#[derive(Template)]
+#[template(path = "experiment.html")]
+pub struct ExperimentTemplate {
+    experiment: &'static Experiment,
+    fragment: String,
+}
+
+#[derive(Template)]
+#[template(path = "dyn_matrix.html")]
+pub struct DynMatrixTemplate {
+    size: u32,
+    cells: Vec<Vec<Cell>>,
+    last_event_id: u64,
+}
+
+#[derive(Template)]
+#[template(path = "experiments.html")]
+pub struct ExperimentsTemplate {
+    pub experiments: &'static [&'static Experiment],
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        blog::Blog, config::Config, limits::ConnectionLimits, matrix::Matrix, resume::ResumeFile,
+        rooms::Lobby, shutdown::Shutdown,
+    };
+
+    static ISOLATED: Experiment = Experiment {
+        slug: "isolated",
+        title: "Isolated",
+        description: "Needs cross-origin isolation.",
+        wasm: Some("ocr"),
+        inline: true,
+        isolated: true,
+        action: "Try!",
+        enabled: true,
+        page: |_, experiment, htmx| inline(OcrTemplate { experiment }, experiment, htmx),
+        routes: |router| router,
+    };
+
+    fn state() -> AppState {
+        let config = Config::default();
+        let missing =
+            std::env::temp_dir().join(format!("mskasal-experiments-{}", std::process::id()));
+        AppState {
+            matrix: Arc::new(Matrix::load(missing.join("matrix.json"), 4, 4)),
+            blog: Arc::new(Blog::load(missing.join("blog"), false)),
+            resume: Arc::new(ResumeFile::load(&config.resume.path).unwrap()),
+            lobby: Arc::new(Lobby::new(config.pong.settings())),
+            shutdown: Arc::new(Shutdown::new()),
+            limits: Arc::new(ConnectionLimits::new(&config.websocket)),
+            pages: Arc::new([]),
+            deployed: crate::dates::from_system_time(std::time::SystemTime::UNIX_EPOCH),
+            config: Arc::new(config),
+        }
+    }
+
+    async fn get(htmx: bool) -> Response {
+        let app = routes(Router::new(), &[&ISOLATED]).with_state(state());
+        let mut request = Request::builder().uri("/isolated");
+        if htmx {
+            request = request.header("hx-request", "true");
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn isolated_pages_are_sent_with_coop_and_coep() {
+        for htmx in [false, true] {
+            let response = get(htmx).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let headers = response.headers();
+            assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+            assert_eq!(headers["cross-origin-embedder-policy"], "require-corp");
+        }
+    }
+
+    #[tokio::test]
+    async fn isolated_pages_are_never_swapped_in_as_fragments() {
+        let body = body(get(true).await).await;
+        assert!(body.starts_with("<!doctype html>"), "{body}");
+        assert!(body.contains("<title>Isolated</title>"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn inline_pages_are_swapped_in_for_htmx() {
+        static INLINE: Experiment = Experiment {
+            isolated: false,
+            ..ISOLATED
+        };
+        let app = routes(Router::new(), &[&INLINE]).with_state(state());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/isolated")
+                    .header("hx-request", "true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key("cross-origin-opener-policy"));
+        assert!(!body(response).await.contains("<!doctype html>"));
+    }
+
+    #[test]
+    fn isolated_experiments_link_to_their_own_page() {
+        assert!(!ISOLATED.swapped_in());
+        assert_eq!(ISOLATED.href(), "/isolated");
+        assert!(REGISTRY[0].swapped_in());
+        assert_eq!(REGISTRY[0].href(), "/experiments#pong");
+    }
+}
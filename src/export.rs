@@ -0,0 +1,344 @@
+use printpdf::{IndirectFontRef, Mm, PdfDocument, PdfLayerReference, TextRenderingMode};
+
+use crate::resume::Resume;
+
+const COLUMNS: usize = 80;
+const PAGE_WIDTH: Mm = Mm(210.0);
+const PAGE_HEIGHT: Mm = Mm(297.0);
+const MARGIN: Mm = Mm(20.0);
+const FONT_SIZE: f32 = 10.0;
+// 12pt leading in millimetres.
+const LINE_HEIGHT: f32 = 12.0 * 25.4 / 72.0;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT.0 - 2.0 * MARGIN.0) / LINE_HEIGHT) as usize;
+// The site's font, which covers the Turkish letters the builtin fonts lack.
+const FONT: &[u8] = include_bytes!("../assets/HackNerdFont-Regular.ttf");
+// There is no bold cut, bold lines are outlined this many points thick.
+const BOLD_OUTLINE: f32 = 0.4;
+
+#[derive(Clone, Copy)]
+enum Style {
+    Regular,
+    Bold,
+}
+
+struct Line {
+    text: String,
+    style: Style,
+}
+
+/// A fixed-width, 80 column rendering of the résumé.
+pub fn text(resume: &Resume) -> String {
+    let mut text = String::new();
+    for line in layout(resume) {
+        text.push_str(line.text.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// The same layout as [`text`], paginated onto A4 in Hack Nerd Font.
+pub fn pdf(resume: &Resume) -> Result<Vec<u8>, printpdf::Error> {
+    let title = format!("{} - Résumé", resume.basics.name);
+    let (document, page, layer) = PdfDocument::new(&title, PAGE_WIDTH, PAGE_HEIGHT, "text");
+    let font = document.add_external_font(FONT)?;
+
+    let lines = layout(resume);
+    let mut pages = paginate(&lines);
+    let mut layer = document.get_page(page).get_layer(layer);
+    if let Some(first) = pages.next() {
+        for (row, line) in first.iter().enumerate() {
+            write_line(&layer, row, line, &font);
+        }
+    }
+    for lines in pages {
+        let (page, index) = document.add_page(PAGE_WIDTH, PAGE_HEIGHT, "text");
+        layer = document.get_page(page).get_layer(index);
+        for (row, line) in lines.iter().enumerate() {
+            write_line(&layer, row, line, &font);
+        }
+    }
+    document.save_to_bytes()
+}
+
+/// Splits `lines` into pages that fit between the top and bottom margins.
+fn paginate(lines: &[Line]) -> std::slice::Chunks<'_, Line> {
+    lines.chunks(LINES_PER_PAGE)
+}
+
+fn write_line(layer: &PdfLayerReference, row: usize, line: &Line, font: &IndirectFontRef) {
+    match line.style {
+        Style::Regular => layer.set_text_rendering_mode(TextRenderingMode::Fill),
+        Style::Bold => {
+            layer.set_outline_thickness(BOLD_OUTLINE);
+            layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+        }
+    }
+    let y = PAGE_HEIGHT.0 - MARGIN.0 - LINE_HEIGHT * (row + 1) as f32;
+    layer.use_text(line.text.as_str(), FONT_SIZE, MARGIN, Mm(y), font);
+}
+
+fn layout(resume: &Resume) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut push = |text: String, style| lines.push(Line { text, style });
+    let basics = &resume.basics;
+
+    wrap(&mut push, &basics.name.to_uppercase(), "", "", Style::Bold);
+    if let Some(label) = &basics.label {
+        wrap(&mut push, label, "", "", Style::Regular);
+    }
+    let location = &basics.location;
+    let location = format!(
+        "{}, {} {}",
+        location.city, location.country, location.postal_code
+    );
+    wrap(&mut push, &location, "", "", Style::Regular);
+    for profile in &basics.profiles {
+        let profile = format!("{}: {}", profile.network, profile.url);
+        wrap(&mut push, &profile, "", "  ", Style::Regular);
+    }
+
+    heading(&mut push, "Summary");
+    paragraph(&mut push, &basics.summary, "", "");
+
+    if !resume.skills.is_empty() {
+        heading(&mut push, "Skills");
+        for skill in &resume.skills {
+            let mut text = skill.keywords.join(" - ");
+            if let Some(level) = &skill.level {
+                text.push_str(&format!(" ({level})"));
+            }
+            paragraph(&mut push, &text, "  - ", "    ");
+        }
+    }
+
+    if !resume.work.is_empty() {
+        heading(&mut push, "Experience");
+        for (index, work) in resume.work.iter().enumerate() {
+            if index > 0 {
+                push(String::new(), Style::Regular);
+            }
+            let title = format!("{}, {} - {}", work.name, work.location, work.position);
+            wrap(&mut push, &title, "", "", Style::Bold);
+            push(work.period(), Style::Regular);
+            for highlight in &work.highlights {
+                paragraph(&mut push, highlight, "  * ", "    ");
+            }
+        }
+    }
+
+    if !resume.education.is_empty() {
+        heading(&mut push, "Education");
+        for (index, education) in resume.education.iter().enumerate() {
+            if index > 0 {
+                push(String::new(), Style::Regular);
+            }
+            let mut title = format!(
+                "{} {}, {}",
+                education.institution, education.area, education.location
+            );
+            if let Some(status) = &education.status {
+                title.push_str(&format!(" - {status}"));
+            }
+            wrap(&mut push, &title, "", "", Style::Bold);
+            push(education.period(), Style::Regular);
+            if let Some(summary) = &education.summary {
+                paragraph(&mut push, summary, "  ", "  ");
+            }
+        }
+    }
+
+    if !resume.publications.is_empty() {
+        heading(&mut push, "Patents");
+        for publication in &resume.publications {
+            paragraph(&mut push, &publication.summary, "", "");
+            let filed = format!(
+                "{} - {} - Filed {}",
+                publication.publisher,
+                publication.name,
+                publication.filed()
+            );
+            wrap(&mut push, &filed, "", "", Style::Regular);
+        }
+    }
+    lines
+}
+
+fn heading(push: &mut impl FnMut(String, Style), title: &str) {
+    push(String::new(), Style::Regular);
+    push(title.to_uppercase(), Style::Bold);
+    push("-".repeat(title.chars().count()), Style::Regular);
+}
+
+/// Word-wraps `markdown` to [`COLUMNS`], prefixing the first and following lines.
+fn paragraph(push: &mut impl FnMut(String, Style), markdown: &str, first: &str, rest: &str) {
+    wrap(push, &plain(markdown), first, rest, Style::Regular);
+}
+
+/// Word-wraps `text` to [`COLUMNS`], breaking words longer than a whole line.
+fn wrap(push: &mut impl FnMut(String, Style), text: &str, first: &str, rest: &str, style: Style) {
+    let mut line = String::from(first);
+    let mut width = first.chars().count();
+    let mut empty = true;
+    for word in text.split_whitespace() {
+        let mut word = word;
+        while !word.is_empty() {
+            if !empty && width + 1 + word.chars().count() > COLUMNS {
+                push(std::mem::replace(&mut line, String::from(rest)), style);
+                width = rest.chars().count();
+                empty = true;
+            }
+            if !empty {
+                line.push(' ');
+                width += 1;
+            }
+            let room = COLUMNS.saturating_sub(width).max(1);
+            let split = word.char_indices().nth(room).map_or(word.len(), |(i, _)| i);
+            line.push_str(&word[..split]);
+            width += word[..split].chars().count();
+            empty = false;
+            word = &word[split..];
+        }
+    }
+    if !empty {
+        push(line, style);
+    }
+}
+
+/// Drops markdown emphasis and turns `[text](url)` links into `text (url)`.
+fn plain(markdown: &str) -> String {
+    let mut plain = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find('[') {
+        let link = rest[start..].split_once("](").and_then(|(text, tail)| {
+            let (url, tail) = tail.split_once(')')?;
+            Some((&text[1..], url, tail))
+        });
+        match link {
+            Some((text, url, tail)) if !text.contains(']') => {
+                plain.push_str(&rest[..start]);
+                plain.push_str(&format!("{text} ({url})"));
+                rest = tail;
+            }
+            _ => {
+                plain.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    plain.push_str(rest);
+    plain.replace('*', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrapped(markdown: &str, first: &str, rest: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        paragraph(&mut |text, _| lines.push(text), markdown, first, rest);
+        lines
+    }
+
+    fn resume() -> Resume {
+        toml::from_str(include_str!("../content/resume.toml")).unwrap()
+    }
+
+    #[test]
+    fn paragraphs_wrap_at_the_column_limit_with_their_prefixes() {
+        let text = "lorem ipsum dolor ".repeat(12);
+        let lines = wrapped(&text, "  * ", "    ");
+        assert!(lines.len() > 1);
+        assert!(lines[0].starts_with("  * lorem"));
+        for line in &lines[1..] {
+            assert!(line.starts_with("    ") && !line.starts_with("     "));
+        }
+        for line in &lines {
+            assert!(line.chars().count() <= COLUMNS, "{line}");
+        }
+        // Lines are filled greedily, the next word would not have fit.
+        for pair in lines.windows(2) {
+            let next = pair[1].split_whitespace().next().unwrap();
+            assert!(pair[0].chars().count() + 1 + next.len() > COLUMNS);
+        }
+        let words = lines
+            .iter()
+            .flat_map(|line| line.trim_start_matches("  * ").split_whitespace())
+            .count();
+        assert_eq!(words, 36);
+    }
+
+    #[test]
+    fn words_longer_than_a_line_are_broken() {
+        let url = format!("https://example.com/{}", "ab".repeat(60));
+        let lines = wrapped(&format!("see {url} here"), "", "  ");
+        assert_eq!(lines[0], "see");
+        assert_eq!(lines[1].chars().count(), COLUMNS);
+        assert!(lines[2].starts_with("  ") && lines[2].ends_with(" here"));
+        assert!(lines[1].starts_with("  https://"));
+        let rejoined = format!("{}{}", lines[1].trim_start(), lines[2].trim_start());
+        assert_eq!(rejoined, format!("{url} here"));
+    }
+
+    #[test]
+    fn empty_paragraphs_push_nothing() {
+        assert!(wrapped("", "  - ", "    ").is_empty());
+        assert!(wrapped(" \n ", "", "").is_empty());
+    }
+
+    #[test]
+    fn plain_drops_emphasis_and_spells_out_links() {
+        assert_eq!(
+            plain("**Rust** at [mskasal](https://mskasal.com), *fast*"),
+            "Rust at mskasal (https://mskasal.com), fast"
+        );
+        assert_eq!(plain("a [b] c [d](e"), "a [b] c [d](e");
+        assert_eq!(plain("[a] [b](c)"), "[a] b (c)");
+    }
+
+    #[test]
+    fn the_font_covers_every_character_written() {
+        let face = ttf_parser::Face::parse(FONT, 0).unwrap();
+        let text = text(&resume()) + "Kaşal Ğüzel İstanbul ılık – Résumé";
+        for c in text.chars().filter(|c| !c.is_control()) {
+            assert!(face.glyph_index(c).is_some(), "{c:?}");
+        }
+    }
+
+    #[test]
+    fn pages_fit_between_the_margins() {
+        let bottom = PAGE_HEIGHT.0 - MARGIN.0 - LINE_HEIGHT * LINES_PER_PAGE as f32;
+        assert!(bottom >= MARGIN.0);
+        assert!(bottom - LINE_HEIGHT < MARGIN.0);
+
+        let lines = |count| -> Vec<Line> {
+            (0..count)
+                .map(|_| Line {
+                    text: String::new(),
+                    style: Style::Regular,
+                })
+                .collect()
+        };
+        assert_eq!(paginate(&lines(0)).count(), 0);
+        assert_eq!(paginate(&lines(LINES_PER_PAGE)).count(), 1);
+        let pages: Vec<_> = paginate(&lines(LINES_PER_PAGE * 2 + 1))
+            .map(<[Line]>::len)
+            .collect();
+        assert_eq!(pages, [LINES_PER_PAGE, LINES_PER_PAGE, 1]);
+    }
+
+    #[test]
+    fn every_line_fits_the_columns() {
+        let mut resume = resume();
+        let long = "x".repeat(COLUMNS);
+        resume.basics.profiles[0].url = format!("https://github.com/{long}");
+        resume.work[0].position = format!("Principal {long}");
+        resume.education[0].area = long.clone();
+        resume.publications[0].publisher = long;
+
+        let text = text(&resume);
+        for line in text.lines() {
+            assert!(line.chars().count() <= COLUMNS, "{line}");
+        }
+        assert!(pdf(&resume).unwrap().starts_with(b"%PDF"));
+    }
+}
@@ -0,0 +1,214 @@
+use std::{any::Any, panic::AssertUnwindSafe, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures_util::FutureExt;
+use tokio::sync::Semaphore;
+
+use crate::{config::RequestConfig, page::ErrorPage};
+
+/// Limits every request: body size, requests in flight, time to respond,
+/// and turns panics into 500 pages.
+pub struct Guard {
+    timeout: Option<Duration>,
+    max_body_bytes: usize,
+    in_flight: Option<Semaphore>,
+    catch_panics: bool,
+}
+
+impl Guard {
+    pub fn new(config: &RequestConfig) -> Self {
+        Guard {
+            timeout: (config.timeout_secs > 0).then(|| Duration::from_secs(config.timeout_secs)),
+            max_body_bytes: config.max_body_bytes,
+            in_flight: (config.max_in_flight > 0).then(|| Semaphore::new(config.max_in_flight)),
+            catch_panics: config.catch_panics,
+        }
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+}
+
+pub async fn guard(State(guard): State<Arc<Guard>>, request: Request, next: Next) -> Response {
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared.is_some_and(|length| length > guard.max_body_bytes) {
+        return ErrorPage(StatusCode::PAYLOAD_TOO_LARGE).into_response();
+    }
+
+    // Shed load rather than queue, a queued request would likely time out.
+    let _permit = match &guard.in_flight {
+        Some(in_flight) => match in_flight.try_acquire() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                tracing::warn!("too many requests in flight");
+                return ErrorPage(StatusCode::SERVICE_UNAVAILABLE).into_response();
+            }
+        },
+        None => None,
+    };
+
+    let path = request.uri().path().to_string();
+    let run = async {
+        if guard.catch_panics {
+            AssertUnwindSafe(next.run(request))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|panic| panicked(&path, panic))
+        } else {
+            next.run(request).await
+        }
+    };
+    let Some(timeout) = guard.timeout else {
+        return run.await;
+    };
+    match tokio::time::timeout(timeout, run).await {
+        Ok(response) => response,
+        Err(_) => {
+            // Our handler was too slow, not the client, so not a 408.
+            tracing::warn!("{path} timed out after {timeout:?}");
+            ErrorPage(StatusCode::SERVICE_UNAVAILABLE).into_response()
+        }
+    }
+}
+
+fn panicked(path: &str, panic: Box<dyn Any + Send>) -> Response {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    tracing::error!("{path} panicked: {message}");
+    ErrorPage(StatusCode::INTERNAL_SERVER_ERROR).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tokio::sync::{oneshot, Notify};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(config: RequestConfig, handler: Router) -> Router {
+        handler.layer(middleware::from_fn_with_state(
+            Arc::new(Guard::new(&config)),
+            guard,
+        ))
+    }
+
+    async fn send(app: &Router, body: &str) -> StatusCode {
+        let request = Request::builder()
+            .uri("/")
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    fn sleeping(duration: Duration) -> Router {
+        Router::new().route(
+            "/",
+            get(move || async move {
+                tokio::time::sleep(duration).await;
+                "done"
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn bodies_declared_too_large_are_refused() {
+        let app = app(
+            RequestConfig {
+                max_body_bytes: 4,
+                ..RequestConfig::default()
+            },
+            Router::new().route("/", get(|| async { "ok" })),
+        );
+        assert_eq!(send(&app, "four").await, StatusCode::OK);
+        assert_eq!(send(&app, "fives").await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_the_in_flight_cap_are_shed() {
+        let entered = Arc::new(Notify::new());
+        let (release, released) = oneshot::channel::<()>();
+        // Only the first request holds on until released.
+        let released = Arc::new(std::sync::Mutex::new(Some(released)));
+        let handler = Router::new().route(
+            "/",
+            get({
+                let entered = Arc::clone(&entered);
+                move || async move {
+                    let released = released.lock().unwrap().take();
+                    if let Some(released) = released {
+                        entered.notify_one();
+                        let _ = released.await;
+                    }
+                    "ok"
+                }
+            }),
+        );
+        let app = app(
+            RequestConfig {
+                max_in_flight: 1,
+                ..RequestConfig::default()
+            },
+            handler,
+        );
+
+        let first = tokio::spawn({
+            let app = app.clone();
+            async move { send(&app, "").await }
+        });
+        entered.notified().await;
+        assert_eq!(send(&app, "").await, StatusCode::SERVICE_UNAVAILABLE);
+
+        release.send(()).unwrap();
+        assert_eq!(first.await.unwrap(), StatusCode::OK);
+        assert_eq!(send(&app, "").await, StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_handlers_time_out_as_unavailable() {
+        let config = RequestConfig {
+            timeout_secs: 2,
+            ..RequestConfig::default()
+        };
+        let slow = app(config.clone(), sleeping(Duration::from_secs(3)));
+        assert_eq!(send(&slow, "").await, StatusCode::SERVICE_UNAVAILABLE);
+
+        let fast = app(config, sleeping(Duration::from_secs(1)));
+        assert_eq!(send(&fast, "").await, StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_zero_timeout_waits_forever() {
+        let app = app(
+            RequestConfig {
+                timeout_secs: 0,
+                ..RequestConfig::default()
+            },
+            sleeping(Duration::from_secs(24 * 60 * 60)),
+        );
+        assert_eq!(send(&app, "").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn panics_become_500s() {
+        let app = app(
+            RequestConfig::default(),
+            Router::new().route("/", get(|| async { panic!("boom") as &str })),
+        );
+        assert_eq!(send(&app, "").await, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,515 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use axum::{
+    extract::ws::{close_code, Message},
+    http::{header, HeaderMap},
+};
+use serde::Deserialize;
+
+use crate::config::WebSocketConfig;
+
+/// Largest `websocket.max_message_bytes` accepted. A frame is buffered whole
+/// before any check runs, so the limit also bounds memory per socket.
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+// Room above the message limit for the protocol layer, so slightly oversized
+// messages still reach `MessageLimit` and get a 1009 close.
+const FRAME_HEADROOM: usize = 4 * 1024;
+
+/// Frame and message size for the websocket protocol layer. Anything larger
+/// ends the socket with a protocol error and no close code.
+pub fn transport_limit(config: &WebSocketConfig) -> usize {
+    config.max_message_bytes + FRAME_HEADROOM
+}
+
+/// An address or CIDR block, e.g. `10.0.0.0/8`, trusted to report the
+/// client's IP in `X-Forwarded-For` or `Forwarded`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl TrustedProxy {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for TrustedProxy {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid trusted proxy `{text}`, expected an IP or CIDR block");
+        let (network, prefix) = match text.split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (text, None),
+        };
+        let network = network.parse::<IpAddr>().map_err(|_| invalid())?;
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(invalid)?,
+            None => bits,
+        };
+        Ok(TrustedProxy {
+            network: network.to_canonical(),
+            prefix,
+        })
+    }
+}
+
+impl TryFrom<String> for TrustedProxy {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        text.parse()
+    }
+}
+
+/// The IP the limits are keyed on. Connections from a trusted proxy are
+/// credited to the nearest untrusted hop in `X-Forwarded-For`, or in
+/// `Forwarded` when that is absent, so clients behind a load balancer do
+/// not share its address.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted: &[TrustedProxy]) -> IpAddr {
+    let peer = peer.to_canonical();
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|proxy| proxy.contains(ip));
+    if !is_trusted(peer) {
+        return peer;
+    }
+
+    let values = |name: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>()
+    };
+    let forwarded_for = values("x-forwarded-for");
+    let hops: Vec<Option<IpAddr>> = if !forwarded_for.is_empty() {
+        forwarded_for
+            .into_iter()
+            .map(|hop| hop.trim().parse().ok())
+            .collect()
+    } else {
+        values(header::FORWARDED.as_str())
+            .into_iter()
+            .map(forwarded_for_param)
+            .collect()
+    };
+
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        // An unparsable hop was written by something untrusted, stop there.
+        let Some(hop) = hop.map(|hop| hop.to_canonical()) else {
+            break;
+        };
+        client = hop;
+        if !is_trusted(hop) {
+            break;
+        }
+    }
+    client
+}
+
+/// The address in the `for=` parameter of one `Forwarded` element, e.g.
+/// `for=192.0.2.60;proto=https` or `for="[2001:db8::1]:4711"`.
+fn forwarded_for_param(element: &str) -> Option<IpAddr> {
+    let value = element.split(';').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("for")
+            .then_some(value.trim())
+    })?;
+    let value = value.trim_matches('"');
+    if let Some(v6) = value.strip_prefix('[') {
+        return v6.split_once(']')?.0.parse().ok();
+    }
+    let host = value.split_once(':').map_or(value, |(host, _)| host);
+    host.parse().ok()
+}
+
+/// Caps open websockets, both in total and per client IP.
+pub struct ConnectionLimits {
+    max_total: usize,
+    max_per_ip: usize,
+    open: Mutex<Open>,
+}
+
+#[derive(Default)]
+struct Open {
+    total: usize,
+    by_ip: HashMap<IpAddr, usize>,
+}
+
+#[derive(Debug)]
+pub enum Rejected {
+    Total(usize),
+    PerIp(usize),
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejected::Total(max) => write!(f, "server already has {max} open websocket(s)"),
+            Rejected::PerIp(max) => write!(f, "client already has {max} open websocket(s)"),
+        }
+    }
+}
+
+/// Holds a connection slot until dropped.
+pub struct Permit {
+    limits: Arc<ConnectionLimits>,
+    ip: IpAddr,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut open = self.limits.open.lock().unwrap();
+        open.total -= 1;
+        if let Some(count) = open.by_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                open.by_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+impl ConnectionLimits {
+    pub fn new(config: &WebSocketConfig) -> Self {
+        ConnectionLimits {
+            max_total: config.max_connections,
+            max_per_ip: config.max_per_ip,
+            open: Mutex::default(),
+        }
+    }
+
+    pub fn acquire(self: &Arc<Self>, ip: IpAddr) -> Result<Permit, Rejected> {
+        let mut open = self.open.lock().unwrap();
+        if open.total >= self.max_total {
+            return Err(Rejected::Total(self.max_total));
+        }
+        let count = open.by_ip.entry(ip).or_default();
+        if *count >= self.max_per_ip {
+            return Err(Rejected::PerIp(self.max_per_ip));
+        }
+        *count += 1;
+        open.total += 1;
+        Ok(Permit {
+            limits: Arc::clone(self),
+            ip,
+        })
+    }
+}
+
+/// Why a socket is being closed, sent to the client in the close frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Violation {
+    pub code: u16,
+    pub reason: &'static str,
+}
+
+/// Per-socket message size check and token bucket rate limit.
+pub struct MessageLimit {
+    max_bytes: usize,
+    per_second: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl MessageLimit {
+    pub fn new(config: &WebSocketConfig) -> Self {
+        let per_second = f64::from(config.messages_per_second);
+        MessageLimit {
+            max_bytes: config.max_message_bytes,
+            per_second,
+            tokens: per_second,
+            refilled: Instant::now(),
+        }
+    }
+
+    pub fn check(&mut self, message: &Message) -> Result<(), Violation> {
+        self.check_at(message, Instant::now())
+    }
+
+    fn check_at(&mut self, message: &Message, now: Instant) -> Result<(), Violation> {
+        let len = match message {
+            Message::Text(text) => text.len(),
+            Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data.len(),
+            Message::Close(_) => return Ok(()),
+        };
+        if len > self.max_bytes {
+            return Err(Violation {
+                code: close_code::SIZE,
+                reason: "message too big",
+            });
+        }
+
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.per_second);
+        self.refilled = now;
+        if self.tokens < 1.0 {
+            return Err(Violation {
+                code: close_code::POLICY,
+                reason: "too many messages",
+            });
+        }
+        self.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use super::*;
+
+    fn config() -> WebSocketConfig {
+        WebSocketConfig {
+            max_connections: 3,
+            max_per_ip: 2,
+            messages_per_second: 5,
+            max_message_bytes: 16,
+            trusted_proxies: Vec::new(),
+        }
+    }
+
+    fn text(len: usize) -> Message {
+        Message::Text("x".repeat(len))
+    }
+
+    fn code(result: Result<(), Violation>) -> Option<u16> {
+        result.err().map(|violation| violation.code)
+    }
+
+    #[test]
+    fn a_burst_past_the_rate_closes_with_policy() {
+        let mut limit = MessageLimit::new(&config());
+        let now = limit.refilled;
+        for _ in 0..5 {
+            assert_eq!(code(limit.check_at(&text(1), now)), None);
+        }
+        assert_eq!(
+            code(limit.check_at(&text(1), now)),
+            Some(close_code::POLICY)
+        );
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_one_second_of_burst() {
+        let mut limit = MessageLimit::new(&config());
+        let start = limit.refilled;
+        for _ in 0..5 {
+            limit.check_at(&text(1), start).unwrap();
+        }
+
+        let later = start + Duration::from_millis(200);
+        assert_eq!(code(limit.check_at(&text(1), later)), None);
+        assert_eq!(
+            code(limit.check_at(&text(1), later)),
+            Some(close_code::POLICY)
+        );
+
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..5 {
+            assert_eq!(code(limit.check_at(&text(1), much_later)), None);
+        }
+        assert_eq!(
+            code(limit.check_at(&text(1), much_later)),
+            Some(close_code::POLICY)
+        );
+    }
+
+    #[test]
+    fn an_oversize_message_closes_with_size() {
+        let mut limit = MessageLimit::new(&config());
+        let now = limit.refilled;
+        assert_eq!(code(limit.check_at(&text(16), now)), None);
+        assert_eq!(code(limit.check_at(&text(17), now)), Some(close_code::SIZE));
+        let binary = Message::Binary(vec![0; 17]);
+        assert_eq!(code(limit.check_at(&binary, now)), Some(close_code::SIZE));
+    }
+
+    #[test]
+    fn close_frames_are_not_counted() {
+        let mut limit = MessageLimit::new(&config());
+        let now = limit.refilled;
+        for _ in 0..10 {
+            assert_eq!(code(limit.check_at(&Message::Close(None), now)), None);
+        }
+        assert_eq!(code(limit.check_at(&text(1), now)), None);
+    }
+
+    #[test]
+    fn the_transport_lets_oversize_messages_through_to_the_check() {
+        let config = config();
+        assert!(transport_limit(&config) > config.max_message_bytes);
+    }
+
+    #[test]
+    fn permits_release_their_slot_on_drop() {
+        let limits = Arc::new(ConnectionLimits::new(&config()));
+        let ip = IpAddr::from(Ipv4Addr::new(192, 0, 2, 1));
+        let first = limits.acquire(ip).unwrap();
+        let second = limits.acquire(ip).unwrap();
+        assert!(matches!(limits.acquire(ip), Err(Rejected::PerIp(2))));
+        assert_eq!(limits.open.lock().unwrap().by_ip[&ip], 2);
+
+        drop(first);
+        assert_eq!(limits.open.lock().unwrap().by_ip[&ip], 1);
+        let third = limits.acquire(ip).unwrap();
+
+        drop(second);
+        drop(third);
+        let open = limits.open.lock().unwrap();
+        assert_eq!(open.total, 0);
+        assert!(!open.by_ip.contains_key(&ip));
+    }
+
+    #[test]
+    fn the_total_cap_covers_every_ip() {
+        let limits = Arc::new(ConnectionLimits::new(&config()));
+        let permits: Vec<_> = (1..=3)
+            .map(|host| limits.acquire(IpAddr::from(Ipv4Addr::new(192, 0, 2, host))))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let other = IpAddr::from(Ipv4Addr::new(192, 0, 2, 4));
+        assert!(matches!(limits.acquire(other), Err(Rejected::Total(3))));
+
+        drop(permits);
+        assert!(limits.acquire(other).is_ok());
+        assert_eq!(limits.open.lock().unwrap().total, 0);
+    }
+
+    fn proxies(blocks: &[&str]) -> Vec<TrustedProxy> {
+        blocks.iter().map(|block| block.parse().unwrap()).collect()
+    }
+
+    fn forwarded(name: &str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            value.parse().unwrap(),
+        );
+        headers
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn trusted_proxies_parse_addresses_and_cidr_blocks() {
+        let block: TrustedProxy = "10.1.0.0/16".parse().unwrap();
+        assert!(block.contains(ip("10.1.255.3")));
+        assert!(!block.contains(ip("10.2.0.1")));
+        assert!(block.contains(ip("::ffff:10.1.0.9")));
+
+        let single: TrustedProxy = "2001:db8::1".parse().unwrap();
+        assert!(single.contains(ip("2001:db8::1")));
+        assert!(!single.contains(ip("2001:db8::2")));
+        assert!("0.0.0.0/0"
+            .parse::<TrustedProxy>()
+            .unwrap()
+            .contains(ip("192.0.2.1")));
+
+        for invalid in ["10.0.0.0/33", "fe80::/129", "10.0.0/8", "lb.internal", ""] {
+            assert!(invalid.parse::<TrustedProxy>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn forwarded_headers_are_ignored_from_untrusted_peers() {
+        let headers = forwarded("x-forwarded-for", "198.51.100.7");
+        assert_eq!(
+            client_ip(ip("203.0.113.9"), &headers, &proxies(&["10.0.0.0/8"])),
+            ip("203.0.113.9")
+        );
+        assert_eq!(client_ip(ip("10.0.0.2"), &headers, &[]), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn the_nearest_untrusted_hop_is_the_client() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        let headers = forwarded("x-forwarded-for", "1.2.3.4, 198.51.100.7, 10.0.0.5");
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &headers, &trusted),
+            ip("198.51.100.7")
+        );
+
+        let only_proxies = forwarded("x-forwarded-for", "10.0.0.9, 10.0.0.5");
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &only_proxies, &trusted),
+            ip("10.0.0.9")
+        );
+
+        let garbled = forwarded("x-forwarded-for", "198.51.100.7, nonsense");
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &garbled, &trusted),
+            ip("10.0.0.2")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &HeaderMap::new(), &trusted),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn the_forwarded_header_is_read_without_x_forwarded_for() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        let headers = forwarded(
+            "forwarded",
+            "for=\"[2001:db8::7]:4711\";proto=https, for=198.51.100.7:80, For=10.0.0.5",
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.2"), &headers, &trusted),
+            ip("198.51.100.7")
+        );
+
+        let v6 = forwarded("forwarded", "proto=https;for=\"[2001:db8::7]:4711\"");
+        assert_eq!(
+            client_ip(ip("::ffff:10.0.0.2"), &v6, &trusted),
+            ip("2001:db8::7")
+        );
+    }
+
+    #[test]
+    fn clients_behind_a_trusted_proxy_get_their_own_permits() {
+        let limits = Arc::new(ConnectionLimits::new(&config()));
+        let trusted = proxies(&["10.0.0.2"]);
+        let first = forwarded("x-forwarded-for", "198.51.100.7");
+        let second = forwarded("x-forwarded-for", "198.51.100.8");
+        let acquire = |headers| limits.acquire(client_ip(ip("10.0.0.2"), headers, &trusted));
+
+        let _first = [acquire(&first).unwrap(), acquire(&first).unwrap()];
+        assert!(matches!(acquire(&first), Err(Rejected::PerIp(2))));
+        assert!(acquire(&second).is_ok());
+    }
+}
@@ -1,87 +1,261 @@
-use std::net::SocketAddr;
+use std::{
+    convert::Infallible,
+    io,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use askama::Template;
 use axum::{
-    extract::{ws::WebSocket, ConnectInfo, Path, Query, State, WebSocketUpgrade},
-    response::IntoResponse,
-    routing::{get, post},
+    extract::{
+        rejection::{PathRejection, QueryRejection},
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        ConnectInfo, DefaultBodyLimit, Path, Query, State, WebSocketUpgrade,
+    },
+    handler::HandlerWithoutStateExt,
+    http::{header, Extensions, HeaderMap, StatusCode, Version},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::{get, MethodRouter},
     Router,
 };
 use axum_extra::{headers, TypedHeader};
+use clap::Parser;
+use futures_util::{stream, Stream, StreamExt};
 use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use toml::value::Datetime;
+use tower::ServiceBuilder;
 use tower_http::{
-    compression::{CompressionLayer, DefaultPredicate},
-    services::{ServeDir, ServeFile},
+    compression::{CompressionLayer, DefaultPredicate, Predicate},
     trace::{DefaultMakeSpan, TraceLayer},
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use assets::Manifest;
+use blog::{Blog, Listing, Post};
+use config::{Cli, Command, Config, ServerConfig};
+use experiments::ExperimentsTemplate;
+use guard::Guard;
+use limits::{ConnectionLimits, MessageLimit, Permit};
+use matrix::{Cell, Change, Matrix, MatrixEvent};
+use metrics::METRICS;
+use page::{ErrorPage, Page};
+use pong_protocol::{ClientMessage, ServerMessage, VERSION};
+use resume::{Loaded, ResumeFile};
+use rooms::{Lobby, LobbyError, Membership};
+use security::SecurityHeaders;
+use shutdown::Shutdown;
+
+mod assets;
+mod blog;
+mod config;
+mod dates;
+#[cfg(feature = "embed")]
+mod embed;
+mod experiments;
+mod export;
+mod guard;
+mod limits;
+mod matrix;
+mod metrics;
+mod page;
+mod precompress;
+mod resume;
+mod rooms;
+mod security;
+mod shutdown;
+
 #[derive(Clone)]
 struct AppState {
-    size: u32,
+    config: Arc<Config>,
+    matrix: Arc<Matrix>,
+    blog: Arc<Blog>,
+    resume: Arc<ResumeFile>,
+    lobby: Arc<Lobby>,
+    shutdown: Arc<Shutdown>,
+    limits: Arc<ConnectionLimits>,
+    pages: Arc<[String]>,
+    deployed: Datetime,
 }
 
+// Routes that are not pages and should stay out of search engines.
+const DISALLOW: [&str; 5] = ["/matrix/", "/ws", "/healthz", "/readyz", "/metrics"];
+const FEED_ENTRIES: usize = 20;
+
 #[derive(Template)]
 #[template(path = "index.html")]
-pub struct IndexTemplate {}
+pub struct IndexTemplate {
+    resume: Arc<Loaded>,
+}
 
 #[derive(Template)]
-#[template(path = "pong.html")]
-pub struct PongTemplate {}
+#[template(path = "dyn_item.html")]
+pub struct DynItemTemplate {
+    i: u32,
+    j: u32,
+    cell: Cell,
+}
 
 #[derive(Template)]
-#[template(path = "ocr.html")]
-pub struct OcrTemplate {}
+#[template(path = "board.html")]
+pub struct BoardTemplate {
+    size: u32,
+    cells: Vec<Vec<Cell>>,
+}
+
+impl BoardTemplate {
+    fn new(cells: Vec<Vec<Cell>>) -> Self {
+        BoardTemplate {
+            size: cells.len() as u32,
+            cells,
+        }
+    }
+}
 
 #[derive(Template)]
-#[template(path = "led_matrix.html")]
-pub struct LedMatrixTemplate {}
+#[template(path = "blog.html")]
+pub struct BlogTemplate {
+    tag: Option<String>,
+    base: String,
+    posts: Vec<Arc<Post>>,
+    page: usize,
+    pages: usize,
+}
+
+impl BlogTemplate {
+    fn new(tag: Option<String>, base: String, listing: Listing) -> Self {
+        BlogTemplate {
+            tag,
+            base,
+            posts: listing.posts,
+            page: listing.page,
+            pages: listing.pages,
+        }
+    }
+}
 
 #[derive(Template)]
-#[template(path = "dyn_matrix.html")]
-pub struct DynMatrixTemplate {
-    size: u32,
+#[template(path = "post.html")]
+pub struct PostTemplate {
+    post: Arc<Post>,
+}
+
+pub struct FeedEntry {
+    post: Arc<Post>,
+    date: String,
 }
 
 #[derive(Template)]
-#[template(path = "dyn_item.html")]
-pub struct DynItemTemplate {
-    i: u32,
-    j: u32,
+#[template(path = "atom.xml")]
+pub struct AtomTemplate {
+    base_url: String,
+    updated: String,
+    entries: Vec<FeedEntry>,
 }
 
 #[derive(Template)]
-#[template(path = "grid.html")]
-pub struct GridTemplate {
-    size: u32,
+#[template(path = "rss.xml")]
+pub struct RssTemplate {
+    base_url: String,
+    updated: String,
+    entries: Vec<FeedEntry>,
+}
+
+pub struct SitemapUrl {
+    path: String,
+    lastmod: String,
 }
 
 #[derive(Template)]
-#[template(path = "experiments.html")]
-pub struct ExperimentsTemplate {}
+#[template(path = "sitemap.xml")]
+pub struct SitemapTemplate {
+    base_url: String,
+    urls: Vec<SitemapUrl>,
+}
+
+#[derive(Template)]
+#[template(path = "robots.txt")]
+pub struct RobotsTemplate {
+    base_url: String,
+    disallow: &'static [&'static str],
+}
 
-async fn index_handler() -> IndexTemplate {
-    IndexTemplate {}
+async fn index_handler(State(state): State<AppState>) -> Page<IndexTemplate> {
+    Page(IndexTemplate {
+        resume: state.resume.current(),
+    })
 }
 
-async fn pong_handler() -> PongTemplate {
-    PongTemplate {}
+async fn resume_json_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let resume = state.resume.current();
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        serde_json::to_string_pretty(&**resume).expect("résumé serializes"),
+    )
 }
 
-async fn ocr_handler() -> OcrTemplate {
-    OcrTemplate {}
+/// Downloads the résumé as e.g. `mustafa-samed-kasal-resume.pdf`.
+fn attachment(resume: &Loaded, extension: &str) -> String {
+    let name: String = resume
+        .basics
+        .name
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    format!("attachment; filename=\"{name}-resume.{extension}\"")
 }
 
-async fn led_matrix_handler() -> LedMatrixTemplate {
-    LedMatrixTemplate {}
+async fn resume_txt_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let resume = state.resume.current();
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                String::from("text/plain; charset=utf-8"),
+            ),
+            (header::CONTENT_DISPOSITION, attachment(&resume, "txt")),
+        ],
+        resume.text().to_string(),
+    )
 }
 
-async fn dyn_matrix_handler(State(state): State<AppState>) -> DynMatrixTemplate {
-    DynMatrixTemplate { size: state.size }
+async fn resume_pdf_handler(State(state): State<AppState>) -> Result<impl IntoResponse, ErrorPage> {
+    let resume = state.resume.current();
+    let disposition = attachment(&resume, "pdf");
+    let pdf = tokio::task::spawn_blocking(move || resume.pdf())
+        .await
+        .map_err(|_| ErrorPage(StatusCode::INTERNAL_SERVER_ERROR))?
+        .map_err(|err| {
+            tracing::error!("failed to render résumé pdf: {err}");
+            ErrorPage(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, String::from("application/pdf")),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        pdf,
+    ))
 }
 
-async fn matrix_state_handler(Path((i, j)): Path<(u32, u32)>) -> DynItemTemplate {
-    DynItemTemplate { i, j }
+async fn matrix_state_handler(
+    State(state): State<AppState>,
+    path: Result<Path<(u32, u32)>, PathRejection>,
+) -> Result<Page<DynItemTemplate>, ErrorPage> {
+    let not_found = ErrorPage(StatusCode::NOT_FOUND);
+    let Path((i, j)) = path.map_err(|_| not_found)?;
+    let cell = state.matrix.flip(i, j).ok_or(not_found)?;
+    Ok(Page(DynItemTemplate { i, j, cell }))
 }
 
 #[derive(Deserialize)]
@@ -90,87 +264,708 @@ struct SizeQuery {
 }
 
 async fn matrix_size_handler(
-    State(mut state): State<AppState>,
-    new_size: Query<SizeQuery>,
-) -> GridTemplate {
-    state.size = new_size.size;
-    GridTemplate { size: state.size }
+    State(state): State<AppState>,
+    new_size: Result<Query<SizeQuery>, QueryRejection>,
+) -> Result<Page<BoardTemplate>, ErrorPage> {
+    let new_size = new_size.map_err(|_| ErrorPage(StatusCode::BAD_REQUEST))?;
+    state
+        .matrix
+        .resize(new_size.size.min(state.config.matrix.max_size));
+    let (_, cells) = state.matrix.rows();
+    Ok(Page(BoardTemplate::new(cells)))
 }
 
-async fn experiments_handler() -> ExperimentsTemplate {
-    ExperimentsTemplate {}
+#[derive(Deserialize)]
+struct EventsQuery {
+    since: Option<u64>,
 }
 
-async fn socket_handler(mut socket: WebSocket, who: SocketAddr) {
-    while let Some(message) = socket.recv().await {
-        let message = if let Ok(message) = message {
-            println!("Message received {who}...");
-            message
-        } else {
-            return;
+fn matrix_event(matrix: &Matrix, event: MatrixEvent) -> Event {
+    let html = match event.change {
+        Change::Cell { i, j, cell } => page::render(&DynItemTemplate { i, j, cell }),
+        Change::Board => page::render(&BoardTemplate::new(matrix.rows().1)),
+    };
+    let name = match event.change {
+        Change::Cell { .. } => "cell",
+        Change::Board => "board",
+    };
+    Event::default()
+        .id(event.id.to_string())
+        .event(name)
+        .data(html.unwrap_or_default())
+}
+
+async fn matrix_events_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or(query.since);
+    let (catch_up, receiver) = state.matrix.subscribe(last_event_id);
+
+    let matrix = Arc::clone(&state.matrix);
+    let live = BroadcastStream::new(receiver).map(move |event| match event {
+        Ok(event) => event,
+        Err(_) => matrix.board_event(),
+    });
+    let matrix = Arc::clone(&state.matrix);
+    let stream = stream::iter(catch_up)
+        .chain(live)
+        .map(move |event| Ok(matrix_event(&matrix, event)))
+        .take_until(state.shutdown.wait());
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    page: Option<usize>,
+}
+
+async fn blog_handler(
+    State(state): State<AppState>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Page<BlogTemplate>, ErrorPage> {
+    let Query(query) = query.map_err(|_| ErrorPage(StatusCode::BAD_REQUEST))?;
+    let listing = state
+        .blog
+        .page(None, query.page.unwrap_or(1), state.config.blog.page_size)
+        .ok_or(ErrorPage(StatusCode::NOT_FOUND))?;
+    Ok(Page(BlogTemplate::new(
+        None,
+        String::from("/blog"),
+        listing,
+    )))
+}
+
+async fn blog_tag_handler(
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+    query: Result<Query<PageQuery>, QueryRejection>,
+) -> Result<Page<BlogTemplate>, ErrorPage> {
+    let Query(query) = query.map_err(|_| ErrorPage(StatusCode::BAD_REQUEST))?;
+    let listing = state
+        .blog
+        .page(
+            Some(&tag),
+            query.page.unwrap_or(1),
+            state.config.blog.page_size,
+        )
+        .ok_or(ErrorPage(StatusCode::NOT_FOUND))?;
+    let base = format!("/blog/tags/{}", blog::path_segment(&tag));
+    Ok(Page(BlogTemplate::new(Some(tag), base, listing)))
+}
+
+async fn post_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Page<PostTemplate>, ErrorPage> {
+    let post = state
+        .blog
+        .post(&slug)
+        .ok_or(ErrorPage(StatusCode::NOT_FOUND))?;
+    Ok(Page(PostTemplate { post }))
+}
+
+fn feed_entries(state: &AppState, format: fn(&Datetime) -> String) -> (String, Vec<FeedEntry>) {
+    let posts = state.blog.posts();
+    let updated = posts.first().map_or(state.deployed, |post| post.date);
+    let entries = posts
+        .iter()
+        .take(FEED_ENTRIES)
+        .map(|post| FeedEntry {
+            post: Arc::clone(post),
+            date: format(&post.date),
+        })
+        .collect();
+    (format(&updated), entries)
+}
+
+async fn atom_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let (updated, entries) = feed_entries(&state, dates::rfc3339);
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        Page(AtomTemplate {
+            base_url: state.config.server.base_url.clone(),
+            updated,
+            entries,
+        }),
+    )
+}
+
+async fn rss_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let (updated, entries) = feed_entries(&state, dates::rfc2822);
+    (
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        Page(RssTemplate {
+            base_url: state.config.server.base_url.clone(),
+            updated,
+            entries,
+        }),
+    )
+}
+
+async fn sitemap_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let deployed = dates::w3c_date(&state.deployed);
+    let pages = state.pages.iter().map(|path| SitemapUrl {
+        path: path.clone(),
+        lastmod: deployed.clone(),
+    });
+    let posts = state.blog.posts();
+    let posts = posts.iter().map(|post| SitemapUrl {
+        path: format!("/blog/{}", blog::path_segment(&post.slug)),
+        lastmod: dates::w3c_date(&post.date),
+    });
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        Page(SitemapTemplate {
+            base_url: state.config.server.base_url.clone(),
+            urls: pages.chain(posts).collect(),
+        }),
+    )
+}
+
+async fn robots_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        Page(RobotsTemplate {
+            base_url: state.config.server.base_url.clone(),
+            disallow: &DISALLOW,
+        }),
+    )
+}
+
+async fn experiments_handler() -> Page<ExperimentsTemplate> {
+    Page(ExperimentsTemplate {
+        experiments: experiments::enabled(),
+    })
+}
+
+async fn healthz_handler() -> &'static str {
+    "ok"
+}
+
+async fn readyz_handler(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    if state.shutdown.is_triggered() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else {
+        (StatusCode::OK, "ready")
+    }
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    METRICS.websockets.set(state.shutdown.websockets() as i64);
+    METRICS.rooms.set(state.lobby.rooms().await as i64);
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.encode(),
+    )
+}
+
+async fn send_message(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+    socket.send(Message::Text(message.encode())).await
+}
+
+async fn send_error(socket: &mut WebSocket, err: LobbyError) -> Result<(), axum::Error> {
+    let message = ServerMessage::Error {
+        message: err.to_string(),
+    };
+    send_message(socket, &message).await
+}
+
+/// Decodes a client message, `None` for control frames that need no reply.
+fn decode(message: Message) -> Option<Result<ClientMessage, LobbyError>> {
+    let text = match message {
+        Message::Text(text) => text,
+        Message::Binary(_) => return Some(Err(LobbyError::BinaryMessage)),
+        Message::Ping(_) | Message::Pong(_) | Message::Close(_) => return None,
+    };
+    Some(match ClientMessage::decode(&text) {
+        Ok(ClientMessage::Join { version, .. }) if version != VERSION => {
+            Err(LobbyError::UnsupportedVersion(version))
+        }
+        Ok(message) => Ok(message),
+        Err(err) => Err(LobbyError::InvalidMessage(err.to_string())),
+    })
+}
+
+async fn close_with(socket: &mut WebSocket, code: u16, reason: &'static str) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+/// Next message from the client, or `None` once the socket is gone or was
+/// closed for breaking the message limits.
+async fn receive(
+    socket: &mut WebSocket,
+    limit: &mut MessageLimit,
+    who: SocketAddr,
+) -> Option<Message> {
+    match socket.recv().await? {
+        Ok(Message::Close(_)) => None,
+        Err(err) => {
+            tracing::warn!("websocket from {who} failed: {err}");
+            None
+        }
+        Ok(message) => match limit.check(&message) {
+            Ok(()) => Some(message),
+            Err(violation) => {
+                tracing::warn!("closing websocket from {who}: {}", violation.reason);
+                close_with(socket, violation.code, violation.reason).await;
+                None
+            }
+        },
+    }
+}
+
+async fn join_room(
+    socket: &mut WebSocket,
+    lobby: &Lobby,
+    limit: &mut MessageLimit,
+    who: SocketAddr,
+) -> Option<Membership> {
+    while let Some(message) = receive(socket, limit, who).await {
+        let joined = match decode(message) {
+            Some(Ok(ClientMessage::Join { target, .. })) => lobby.enter(target).await,
+            Some(Ok(ClientMessage::Input { .. })) => Err(LobbyError::NotInRoom),
+            Some(Ok(ClientMessage::Ping { nonce })) => {
+                send_message(socket, &ServerMessage::Pong { nonce })
+                    .await
+                    .ok()?;
+                continue;
+            }
+            Some(Err(err)) => Err(err),
+            None => continue,
         };
+        match joined {
+            Ok(membership) => return Some(membership),
+            Err(err) => send_error(socket, err).await.ok()?,
+        }
+    }
+    None
+}
+
+async fn socket_handler(mut socket: WebSocket, who: SocketAddr, state: AppState, _permit: Permit) {
+    let _tracked = state.shutdown.track_websocket();
+    let stopping = state.shutdown.wait();
+    tokio::pin!(stopping);
+    let mut limit = MessageLimit::new(&state.config.websocket);
 
-        if socket.send(message).await.is_err() {
+    // Private rooms that close send the player back here to pick another.
+    'seating: loop {
+        let membership = tokio::select! {
+            membership = join_room(&mut socket, &state.lobby, &mut limit, who) => membership,
+            _ = &mut stopping => {
+                close_with(&mut socket, close_code::RESTART, "server restarting").await;
+                return;
+            }
+        };
+        let Some(mut membership) = membership else {
             return;
+        };
+        tracing::debug!(
+            "{who} joined room {} as {:?}",
+            membership.room,
+            membership.side
+        );
+        if send_message(&mut socket, &membership.welcome())
+            .await
+            .is_err()
+        {
+            membership.leave().await;
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                message = receive(&mut socket, &mut limit, who) => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    let sent = match decode(message) {
+                        Some(Ok(ClientMessage::Input { direction })) => {
+                            membership.input(direction).await;
+                            Ok(())
+                        }
+                        Some(Ok(ClientMessage::Ping { nonce })) => {
+                            send_message(&mut socket, &ServerMessage::Pong { nonce }).await
+                        }
+                        Some(Ok(ClientMessage::Join { .. })) => {
+                            send_error(&mut socket, LobbyError::AlreadyInRoom).await
+                        }
+                        Some(Err(err)) => send_error(&mut socket, err).await,
+                        None => Ok(()),
+                    };
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                event = membership.events.recv() => match event {
+                    Ok(event) => {
+                        if send_message(&mut socket, &event).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    // The room gave up on the opponent. Matchmade players get a
+                    // new one, strangers stay out of private rooms.
+                    Err(RecvError::Closed) if !membership.queued => {
+                        tracing::debug!("{who} left abandoned room {}", membership.room);
+                        if send_error(&mut socket, LobbyError::RoomClosed).await.is_err() {
+                            return;
+                        }
+                        continue 'seating;
+                    }
+                    Err(RecvError::Closed) => {
+                        if send_error(&mut socket, LobbyError::OpponentLeft).await.is_err() {
+                            return;
+                        }
+                        let Ok(requeued) = state.lobby.queue().await else {
+                            break;
+                        };
+                        tracing::debug!(
+                            "{who} left abandoned room {} for room {}",
+                            membership.room,
+                            requeued.room
+                        );
+                        membership = requeued;
+                        if send_message(&mut socket, &membership.welcome()).await.is_err() {
+                            break;
+                        }
+                    }
+                },
+                _ = &mut stopping => {
+                    close_with(&mut socket, close_code::RESTART, "server restarting").await;
+                    break;
+                }
+            }
         }
+
+        tracing::debug!("{who} left room {}", membership.room);
+        membership.leave().await;
+        return;
     }
 }
 
 async fn ws_handler(
+    State(state): State<AppState>,
     ws: WebSocketUpgrade,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let user_agent = if let Some(TypedHeader(user_agent)) = user_agent {
         user_agent.to_string()
     } else {
         String::from("Unknown browser")
     };
-    println!("`{user_agent}` at {addr} connected.");
-    ws.on_upgrade(move |socket| socket_handler(socket, addr))
+    let transport_limit = limits::transport_limit(&state.config.websocket);
+    let ws = ws
+        .max_message_size(transport_limit)
+        .max_frame_size(transport_limit);
+    let ip = limits::client_ip(addr.ip(), &headers, &state.config.websocket.trusted_proxies);
+    match state.limits.acquire(ip) {
+        Ok(permit) => {
+            tracing::debug!("`{user_agent}` at {ip} via {addr} connected");
+            ws.on_upgrade(move |socket| socket_handler(socket, addr, state, permit))
+        }
+        Err(rejected) => {
+            tracing::warn!("refusing websocket from {ip} via {addr}: {rejected}");
+            ws.on_upgrade(|mut socket| async move {
+                close_with(&mut socket, close_code::AGAIN, "too many connections").await;
+            })
+        }
+    }
+}
+
+/// Reloads blog posts and the résumé whenever the process receives SIGHUP.
+fn spawn_reload(blog: Arc<Blog>, resume: Arc<ResumeFile>) {
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup()).expect("failed to install SIGHUP handler");
+        while hangup.recv().await.is_some() {
+            let blog = Arc::clone(&blog);
+            let resume = Arc::clone(&resume);
+            let reload = tokio::task::spawn_blocking(move || {
+                match blog.reload() {
+                    Ok(count) => tracing::info!("reloaded {count} post(s)"),
+                    Err(err) => tracing::error!(
+                        "failed to reload posts from {}: {err}",
+                        blog.dir().display()
+                    ),
+                }
+                match resume.reload() {
+                    Ok(()) => tracing::info!("reloaded résumé"),
+                    Err(err) => tracing::error!(
+                        "failed to reload résumé from {}: {err}",
+                        resume.path().display()
+                    ),
+                }
+            });
+            if let Err(err) = reload.await {
+                tracing::error!("reload panicked: {err}");
+            }
+        }
+    });
+}
+
+/// Precompresses and fingerprints the assets in `server.assets_dir`.
+#[cfg(not(feature = "embed"))]
+async fn prepare_assets(config: &ServerConfig) -> io::Result<Manifest> {
+    if config.precompress {
+        let dir = config.assets_dir.clone();
+        match tokio::task::spawn_blocking(move || precompress::precompress(&dir))
+            .await
+            .expect("precompress task panicked")
+        {
+            Ok(summary) => tracing::info!("precompressed assets: {summary}"),
+            Err(err) => tracing::warn!("cannot precompress assets, serving them as is: {err}"),
+        }
+    }
+    Manifest::build(&config.assets_dir)
+}
+
+/// Precompresses and fingerprints the assets embedded in the binary.
+#[cfg(feature = "embed")]
+async fn prepare_assets(config: &ServerConfig) -> io::Result<Manifest> {
+    if config.precompress {
+        let summary = tokio::task::spawn_blocking(embed::precompress)
+            .await
+            .expect("precompress task panicked");
+        tracing::info!("precompressed embedded assets: {summary}");
+    }
+    Ok(embed::manifest())
+}
+
+#[cfg(not(feature = "embed"))]
+fn asset_routes(config: &ServerConfig) -> Router {
+    use tower_http::services::{ServeDir, ServeFile};
+
+    Router::new()
+        .nest_service(
+            "/favicon.ico",
+            ServeFile::new(config.assets_dir.join("favicon.ico"))
+                .precompressed_br()
+                .precompressed_zstd()
+                .precompressed_gzip(),
+        )
+        .nest_service(
+            "/assets",
+            ServiceBuilder::new()
+                .layer(middleware::from_fn(assets::cache))
+                .service(
+                    ServeDir::new(&config.assets_dir)
+                        .precompressed_br()
+                        .precompressed_zstd()
+                        .precompressed_gzip()
+                        .not_found_service(page::not_found.into_service()),
+                ),
+        )
+}
+
+#[cfg(feature = "embed")]
+fn asset_routes(_: &ServerConfig) -> Router {
+    Router::new()
+        .route("/favicon.ico", get(embed::favicon))
+        .nest_service(
+            "/assets",
+            ServiceBuilder::new()
+                .layer(middleware::from_fn(assets::cache))
+                .service(embed::serve.into_service()),
+        )
 }
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
-    let state = AppState { size: 40 };
-    let comression_layer: CompressionLayer = CompressionLayer::new()
-        .br(true)
-        .gzip(true)
-        .zstd(true)
-        .compress_when(DefaultPredicate::new());
+    let cli = Cli::parse();
+    let config = match Config::load(&cli) {
+        Ok(config) => Arc::new(config),
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(2);
+        }
+    };
 
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "example_websockets=debug,tower_http=debug".into()),
-        )
+        .with(tracing_subscriber::EnvFilter::new(&config.log.filter))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let app = Router::new()
-        .route("/", get(index_handler))
-        .route("/pong", get(pong_handler))
-        .route("/ocr", get(ocr_handler))
-        .route("/led_matrix", get(led_matrix_handler))
-        .route("/dyn_matrix", get(dyn_matrix_handler))
-        .route("/matrix/size", get(matrix_size_handler))
-        .route("/matrix/:i/:j", get(matrix_state_handler))
-        .route("/experiments", get(experiments_handler))
-        .route("/ws", get(ws_handler))
+    if let Some(Command::Precompress) = cli.command {
+        match precompress::precompress(&config.server.assets_dir) {
+            Ok(summary) => tracing::info!("precompressed assets: {summary}"),
+            Err(err) => {
+                eprintln!(
+                    "error: cannot precompress {}: {err}",
+                    config.server.assets_dir.display()
+                );
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let resume = match ResumeFile::load(&config.resume.path) {
+        Ok(resume) => Arc::new(resume),
+        Err(err) => {
+            eprintln!(
+                "error: cannot load résumé {}: {err}",
+                config.resume.path.display()
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let matrix = Arc::new(Matrix::load(
+        &config.matrix.snapshot,
+        config.matrix.default_size,
+        config.matrix.max_size,
+    ));
+    Arc::clone(&matrix).spawn_persistence();
+    let blog = Arc::new(Blog::load(&config.blog.content_dir, config.blog.drafts));
+    spawn_reload(Arc::clone(&blog), Arc::clone(&resume));
+    let shutdown = Arc::new(Shutdown::new());
+    experiments::configure(&config.experiments);
+    match prepare_assets(&config.server).await {
+        Ok(manifest) => {
+            tracing::debug!("fingerprinted {} asset(s)", manifest.len());
+            assets::configure(manifest);
+        }
+        Err(err) => {
+            eprintln!(
+                "error: cannot read assets {}: {err}",
+                config.server.assets_dir.display()
+            );
+            std::process::exit(2);
+        }
+    }
+    let pages: [(&'static str, MethodRouter<AppState>); 3] = [
+        ("/", get(index_handler)),
+        ("/blog", get(blog_handler)),
+        ("/experiments", get(experiments_handler)),
+    ];
+    // The binary's mtime stands in for when the page templates last changed.
+    let deployed = std::env::current_exe()
+        .and_then(|path| path.metadata())
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or_else(|_| SystemTime::now());
+    let state = AppState {
+        config: Arc::clone(&config),
+        matrix: Arc::clone(&matrix),
+        blog,
+        resume,
+        lobby: Arc::new(Lobby::new(config.pong.settings())),
+        shutdown: Arc::clone(&shutdown),
+        limits: Arc::new(ConnectionLimits::new(&config.websocket)),
+        pages: pages
+            .iter()
+            .map(|(path, _)| path.to_string())
+            .chain(experiments::enabled().iter().map(|e| e.path()))
+            .collect(),
+        deployed: dates::from_system_time(deployed),
+    };
+    let comression_layer = CompressionLayer::new()
+        .br(true)
+        .gzip(true)
+        .zstd(true)
+        .compress_when(DefaultPredicate::new().and(
+            |_: StatusCode, _: Version, _: &HeaderMap, extensions: &Extensions| {
+                extensions.get::<assets::Static>().is_none()
+            },
+        ));
+
+    let guard = Arc::new(Guard::new(&config.requests));
+
+    let app = pages
+        .into_iter()
+        .fold(Router::new(), |router, (path, page)| {
+            router.route(path, page)
+        });
+    let app = experiments::routes(app, experiments::enabled())
+        .route("/resume.json", get(resume_json_handler))
+        .route("/resume.txt", get(resume_txt_handler))
+        .route("/resume.pdf", get(resume_pdf_handler))
+        .route("/blog/tags/:tag", get(blog_tag_handler))
+        .route("/blog/:slug", get(post_handler))
+        .route("/feed.xml", get(atom_handler))
+        .route("/rss.xml", get(rss_handler))
+        .route("/sitemap.xml", get(sitemap_handler))
+        .route("/robots.txt", get(robots_handler))
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .route("/metrics", get(metrics_handler))
+        .fallback(page::not_found)
         .with_state(state)
-        .nest_service("/favicon.ico", ServeFile::new("assets/favicon.ico"))
-        .nest_service("/assets", ServeDir::new("assets"))
+        .merge(asset_routes(&config.server))
+        .route_layer(middleware::from_fn(metrics::route))
+        .layer(DefaultBodyLimit::max(guard.max_body_bytes()))
+        .layer(middleware::from_fn_with_state(guard, guard::guard))
+        .layer(middleware::from_fn(page::error_pages))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(SecurityHeaders::new(&config.security)),
+            security::headers,
+        ))
         .layer(comression_layer)
+        .layer(middleware::from_fn(metrics::track))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::default().include_headers(true)),
-        );
+        )
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(&shutdown),
+            shutdown::track_requests,
+        ));
+
+    let listener = tokio::net::TcpListener::bind(config.server.bind)
+        .await
+        .unwrap_or_else(|err| panic!("cannot bind {}: {err}", config.server.bind));
+    tracing::info!("listening on {}", config.server.bind);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await.unwrap();
-    axum::serve(
+    let stopping = Arc::clone(&shutdown);
+    let server = axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
-    .await
-    .unwrap();
+    .with_graceful_shutdown(async move {
+        shutdown::signal().await;
+        stopping.trigger();
+    });
+
+    let drain_timeout = Duration::from_secs(config.server.drain_timeout_secs);
+    let drained = async {
+        server.await.expect("server error");
+        shutdown.drained().await;
+        true
+    };
+    let deadline = async {
+        shutdown.wait().await;
+        tokio::time::sleep(drain_timeout).await;
+        false
+    };
+    let drained = tokio::select! {
+        drained = drained => drained,
+        drained = deadline => drained,
+    };
+    if !drained {
+        tracing::warn!("drain timeout of {drain_timeout:?} elapsed, dropping open connections");
+    }
+
+    if let Err(err) = matrix.persist().await {
+        tracing::error!("failed to persist matrix on shutdown: {err}");
+    }
+    let (websockets, requests) = shutdown.in_flight();
+    tracing::info!(
+        "shutdown complete: drained {}/{websockets} websocket(s) and {}/{requests} request(s)",
+        websockets.saturating_sub(shutdown.websockets()),
+        requests.saturating_sub(shutdown.requests()),
+    );
 }
@@ -0,0 +1,321 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+pub const DEFAULT_SIZE: u32 = 40;
+pub const MAX_SIZE: u32 = 100;
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+const HISTORY: usize = 256;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Cell {
+    pub flipped: bool,
+    pub color: (u8, u8, u8),
+}
+
+impl Cell {
+    pub fn style(&self) -> String {
+        if self.flipped {
+            let (red, green, blue) = self.color;
+            format!("background-color: rgb({red}, {green}, {blue});")
+        } else {
+            String::new()
+        }
+    }
+
+    fn flip(&mut self) {
+        self.flipped = !self.flipped;
+        if self.flipped {
+            let mut rng = rand::thread_rng();
+            self.color = (rng.gen(), rng.gen(), rng.gen());
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Change {
+    Cell { i: u32, j: u32, cell: Cell },
+    Board,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatrixEvent {
+    pub id: u64,
+    pub change: Change,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCell {
+    i: u32,
+    j: u32,
+    #[serde(flatten)]
+    cell: Cell,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    size: u32,
+    cells: Vec<StoredCell>,
+    /// Viewers keep their `Last-Event-ID` across restarts, so ids carry on
+    /// from here rather than starting over.
+    #[serde(default)]
+    last_event_id: u64,
+}
+
+struct Board {
+    size: u32,
+    cells: HashMap<(u32, u32), Cell>,
+    last_event_id: u64,
+    history: VecDeque<MatrixEvent>,
+}
+
+impl Board {
+    fn new(size: u32, cells: HashMap<(u32, u32), Cell>) -> Self {
+        Board {
+            size,
+            cells,
+            last_event_id: 0,
+            history: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    fn restore(snapshot: Snapshot, max_size: u32) -> Self {
+        let size = snapshot.size.clamp(1, max_size);
+        let cells = snapshot
+            .cells
+            .into_iter()
+            .filter(|stored| stored.i < size && stored.j < size)
+            .map(|stored| ((stored.i, stored.j), stored.cell))
+            .collect();
+        Board {
+            last_event_id: snapshot.last_event_id,
+            ..Board::new(size, cells)
+        }
+    }
+
+    fn record(&mut self, change: Change) -> MatrixEvent {
+        self.last_event_id += 1;
+        let event = MatrixEvent {
+            id: self.last_event_id,
+            change,
+        };
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(event.clone());
+        event
+    }
+
+    fn board_event(&self) -> MatrixEvent {
+        MatrixEvent {
+            id: self.last_event_id,
+            change: Change::Board,
+        }
+    }
+
+    fn since(&self, last_event_id: u64) -> Option<Vec<MatrixEvent>> {
+        if last_event_id > self.last_event_id {
+            return None;
+        }
+        let oldest = self
+            .history
+            .front()
+            .map_or(self.last_event_id, |event| event.id - 1);
+        if last_event_id < oldest {
+            return None;
+        }
+        Some(
+            self.history
+                .iter()
+                .filter(|event| event.id > last_event_id)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            size: self.size,
+            cells: self
+                .cells
+                .iter()
+                .map(|(&(i, j), &cell)| StoredCell { i, j, cell })
+                .collect(),
+            last_event_id: self.last_event_id,
+        }
+    }
+}
+
+pub struct Matrix {
+    board: RwLock<Board>,
+    events: broadcast::Sender<MatrixEvent>,
+    path: PathBuf,
+    dirty: AtomicBool,
+}
+
+impl Matrix {
+    pub fn load(path: impl Into<PathBuf>, default_size: u32, max_size: u32) -> Self {
+        let path = path.into();
+        let board = match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Snapshot>(&bytes) {
+                Ok(snapshot) => Board::restore(snapshot, max_size),
+                Err(err) => {
+                    tracing::warn!("ignoring corrupt matrix snapshot {}: {err}", path.display());
+                    Board::new(default_size, HashMap::new())
+                }
+            },
+            Err(_) => Board::new(default_size, HashMap::new()),
+        };
+        Matrix {
+            board: RwLock::new(board),
+            events: broadcast::channel(HISTORY).0,
+            path,
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    pub fn rows(&self) -> (u64, Vec<Vec<Cell>>) {
+        let board = self.board.read().unwrap();
+        let rows = (0..board.size)
+            .map(|i| {
+                (0..board.size)
+                    .map(|j| board.cells.get(&(i, j)).copied().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        (board.last_event_id, rows)
+    }
+
+    pub fn resize(&self, size: u32) {
+        let size = size.max(1);
+        let mut board = self.board.write().unwrap();
+        board.size = size;
+        board.cells.retain(|&(i, j), _| i < size && j < size);
+        let event = board.record(Change::Board);
+        let _ = self.events.send(event);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    pub fn flip(&self, i: u32, j: u32) -> Option<Cell> {
+        let mut board = self.board.write().unwrap();
+        if i >= board.size || j >= board.size {
+            return None;
+        }
+        let cell = board.cells.entry((i, j)).or_default();
+        cell.flip();
+        let cell = *cell;
+        let event = board.record(Change::Cell { i, j, cell });
+        let _ = self.events.send(event);
+        self.dirty.store(true, Ordering::Relaxed);
+        Some(cell)
+    }
+
+    pub fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> (Vec<MatrixEvent>, broadcast::Receiver<MatrixEvent>) {
+        let board = self.board.read().unwrap();
+        let receiver = self.events.subscribe();
+        let catch_up = last_event_id
+            .and_then(|last_event_id| board.since(last_event_id))
+            .unwrap_or_else(|| vec![board.board_event()]);
+        (catch_up, receiver)
+    }
+
+    pub fn board_event(&self) -> MatrixEvent {
+        self.board.read().unwrap().board_event()
+    }
+
+    pub async fn persist(&self) -> io::Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let bytes = {
+            let board = self.board.read().unwrap();
+            serde_json::to_vec(&board.snapshot())?
+        };
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &self.path).await
+    }
+
+    pub fn spawn_persistence(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PERSIST_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.persist().await {
+                    self.dirty.store(true, Ordering::Relaxed);
+                    tracing::error!("failed to persist matrix to {}: {err}", self.path.display());
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saves `matrix` and loads it back, as a restart would.
+    fn restart(matrix: &Matrix, name: &str) -> Matrix {
+        let path = std::env::temp_dir().join(format!("matrix-{}-{name}.json", std::process::id()));
+        let snapshot = matrix.board.read().unwrap().snapshot();
+        std::fs::write(&path, serde_json::to_vec(&snapshot).unwrap()).unwrap();
+        let restarted = Matrix::load(&path, DEFAULT_SIZE, MAX_SIZE);
+        std::fs::remove_file(&path).unwrap();
+        restarted
+    }
+
+    fn ids(events: &[MatrixEvent]) -> Vec<u64> {
+        events.iter().map(|event| event.id).collect()
+    }
+
+    #[test]
+    fn event_ids_carry_on_after_a_restart() {
+        let matrix = Matrix::load("/nonexistent/matrix.json", 4, MAX_SIZE);
+        for _ in 0..10 {
+            matrix.flip(0, 0);
+        }
+        let (seen, _) = matrix.rows();
+        assert_eq!(seen, 10);
+
+        let matrix = restart(&matrix, "carry-on");
+        let (catch_up, _) = matrix.subscribe(Some(seen));
+        assert!(catch_up.is_empty());
+
+        for _ in 0..12 {
+            matrix.flip(1, 1);
+        }
+        let (catch_up, _) = matrix.subscribe(Some(seen));
+        assert_eq!(ids(&catch_up), (11..=22).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn viewers_behind_a_restart_get_the_whole_board() {
+        let matrix = Matrix::load("/nonexistent/matrix.json", 4, MAX_SIZE);
+        for _ in 0..10 {
+            matrix.flip(0, 0);
+        }
+
+        let matrix = restart(&matrix, "behind");
+        matrix.flip(1, 1);
+        let (catch_up, _) = matrix.subscribe(Some(9));
+        assert_eq!(ids(&catch_up), [11]);
+        assert!(matches!(catch_up[0].change, Change::Board));
+    }
+}
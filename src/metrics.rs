@@ -0,0 +1,238 @@
+use std::{
+    sync::{Arc, LazyLock, OnceLock},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    pub websockets: IntGauge,
+    pub rooms: IntGauge,
+    render_errors: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some(String::from("mskasal")), None)
+            .expect("valid metrics prefix");
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let websockets =
+            IntGauge::new("websocket_connections", "Open /ws connections").expect("valid metric");
+        let rooms = IntGauge::new("pong_rooms", "Open Pong rooms").expect("valid metric");
+        let render_errors = IntCounterVec::new(
+            Opts::new("template_render_errors_total", "Failed template renders"),
+            &["template"],
+        )
+        .expect("valid metric");
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry.register(Box::new(latency.clone())).unwrap();
+        registry.register(Box::new(websockets.clone())).unwrap();
+        registry.register(Box::new(rooms.clone())).unwrap();
+        registry.register(Box::new(render_errors.clone())).unwrap();
+
+        Metrics {
+            registry,
+            requests,
+            latency,
+            websockets,
+            rooms,
+            render_errors,
+        }
+    }
+
+    pub fn render_error(&self, template: &str) {
+        self.render_errors.with_label_values(&[template]).inc();
+    }
+
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding never fails");
+        String::from_utf8(buffer).expect("prometheus text is utf-8")
+    }
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+// Label for requests refused before routing or left to the fallback, so
+// probing random paths cannot grow the label set.
+const UNMATCHED: &str = "unmatched";
+
+/// The method as a label, anything non-standard as "other" for the same reason.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::PATCH => "PATCH",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+/// Where [`route`] leaves the matched route for [`track`].
+#[derive(Clone, Default)]
+struct Route(Arc<OnceLock<String>>);
+
+/// Records count and latency for every request, labelled with the route it
+/// matched. Runs outside the guard so refused, timed out and panicking
+/// requests are counted too.
+pub async fn track(mut request: Request, next: Next) -> Response {
+    let method = method_label(request.method());
+    let route = Route::default();
+    request.extensions_mut().insert(route.clone());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    let route = route.0.get().map_or(UNMATCHED, String::as_str);
+    METRICS
+        .latency
+        .with_label_values(&[method, route])
+        .observe(start.elapsed().as_secs_f64());
+    METRICS
+        .requests
+        .with_label_values(&[method, route, response.status().as_str()])
+        .inc();
+    response
+}
+
+/// Notes the route a request matched for [`track`], as a route layer.
+pub async fn route(request: Request, next: Next) -> Response {
+    if let Some(Route(slot)) = request.extensions().get::<Route>() {
+        let route = match request.extensions().get::<MatchedPath>() {
+            Some(path) => path.as_str().to_string(),
+            None => nested_label(request.uri().path()),
+        };
+        let _ = slot.set(route);
+    }
+    next.run(request).await
+}
+
+// Nested services don't get a MatchedPath, label them by their mount point.
+fn nested_label(path: &str) -> String {
+    let mut segments = path.trim_start_matches('/').splitn(2, '/');
+    match (segments.next(), segments.next()) {
+        (Some(mount), Some(_)) => format!("/{mount}/*"),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        config::RequestConfig,
+        guard::{self, Guard},
+    };
+
+    fn count(method: &str, route: &str, status: StatusCode) -> u64 {
+        METRICS
+            .requests
+            .with_label_values(&[method, route, status.as_str()])
+            .get()
+    }
+
+    async fn send(app: &Router, method: &str, path: &str, body: &str) -> StatusCode {
+        let request = Request::builder()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri(path)
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn requests_are_counted_even_when_refused_or_unrouted() {
+        let guard = Arc::new(Guard::new(&RequestConfig {
+            max_body_bytes: 4,
+            ..RequestConfig::default()
+        }));
+        let app = Router::new()
+            .route(
+                "/metrics-test/:id",
+                get(|| async { "ok" }).post(|| async { panic!("boom") as &str }),
+            )
+            .fallback(|| async { StatusCode::NOT_FOUND })
+            .route_layer(middleware::from_fn(route))
+            .layer(middleware::from_fn_with_state(guard, guard::guard))
+            .layer(middleware::from_fn(track));
+
+        assert_eq!(
+            send(&app, "GET", "/metrics-test/1", "").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, "GET", "/metrics-test/2", "").await,
+            StatusCode::OK
+        );
+        assert_eq!(count("GET", "/metrics-test/:id", StatusCode::OK), 2);
+
+        let panicked = send(&app, "POST", "/metrics-test/1", "").await;
+        assert_eq!(panicked, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(count("POST", "/metrics-test/:id", panicked), 1);
+
+        // Nothing else counts unmatched requests with non-standard methods.
+        let refused = send(&app, "REFUSE", "/metrics-test/1", "too long").await;
+        assert_eq!(refused, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(count("other", UNMATCHED, refused), 1);
+        assert_eq!(count("REFUSE", UNMATCHED, refused), 0);
+
+        let missing = send(&app, "PROBE", "/no/such/page", "").await;
+        assert_eq!(missing, StatusCode::NOT_FOUND);
+        assert_eq!(count("other", UNMATCHED, missing), 1);
+        assert_eq!(count("PROBE", UNMATCHED, missing), 0);
+        assert_eq!(count("other", "/no/such/page", missing), 0);
+    }
+
+    #[test]
+    fn non_standard_methods_share_one_label() {
+        assert_eq!(method_label(&Method::GET), "GET");
+        assert_eq!(method_label(&Method::OPTIONS), "OPTIONS");
+        assert_eq!(method_label(&Method::CONNECT), "other");
+        assert_eq!(method_label(&Method::from_bytes(b"FOO1").unwrap()), "other");
+    }
+
+    #[test]
+    fn nested_services_are_labelled_by_mount_point() {
+        assert_eq!(nested_label("/assets/app-1234.js"), "/assets/*");
+        assert_eq!(nested_label("/favicon.ico"), "/favicon.ico");
+    }
+}
@@ -0,0 +1,246 @@
+use std::convert::Infallible;
+
+use askama::Template;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::metrics::METRICS;
+
+#[derive(Template)]
+#[template(path = "404.html")]
+pub struct NotFoundTemplate {
+    path: String,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorTemplate {
+    status: u16,
+    reason: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "error_fragment.html")]
+pub struct ErrorFragmentTemplate {
+    status: u16,
+    reason: &'static str,
+}
+
+/// Renders `template`, logging and counting failures.
+pub fn render<T: Template>(template: &T) -> Result<String, askama::Error> {
+    template.render().inspect_err(|err| {
+        let name = std::any::type_name::<T>()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default();
+        tracing::error!("failed to render {name}: {err}");
+        METRICS.render_error(name);
+    })
+}
+
+pub struct Page<T>(pub T);
+
+impl<T: Template> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        match render(&self.0) {
+            Ok(body) => ([(header::CONTENT_TYPE, T::MIME_TYPE)], body).into_response(),
+            Err(_) => ErrorPage(StatusCode::INTERNAL_SERVER_ERROR).into_response(),
+        }
+    }
+}
+
+/// An error status whose body is filled in by [`error_pages`].
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorPage(pub StatusCode);
+
+impl IntoResponse for ErrorPage {
+    fn into_response(self) -> Response {
+        let mut response = self.0.into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Request headers that decide between a fragment and a full page, for `Vary`.
+pub const HTMX_VARY: &str = "HX-Request, HX-Boosted, HX-History-Restore-Request";
+
+/// How htmx asked for a page. Swaps want a fragment, while boosted links and
+/// history restores replace the whole document.
+#[derive(Clone, Copy, Debug)]
+pub struct Htmx {
+    fragment: bool,
+}
+
+impl Htmx {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let set = |name| headers.get(name).is_some_and(|value| value == "true");
+        Htmx {
+            fragment: set("hx-request") && !set("hx-boosted") && !set("hx-history-restore-request"),
+        }
+    }
+
+    pub fn wants_fragment(self) -> bool {
+        self.fragment
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Htmx {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Infallible> {
+        Ok(Htmx::from_headers(&parts.headers))
+    }
+}
+
+pub async fn not_found() -> ErrorPage {
+    ErrorPage(StatusCode::NOT_FOUND)
+}
+
+/// Renders [`ErrorPage`] responses as full pages, or as fragments for htmx,
+/// varying on the headers that pick one.
+pub async fn error_pages(request: Request, next: Next) -> Response {
+    let htmx = Htmx::from_headers(request.headers()).wants_fragment();
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+    let Some(&ErrorPage(status)) = response.extensions().get::<ErrorPage>() else {
+        return response;
+    };
+
+    let code = status.as_u16();
+    let reason = status.canonical_reason().unwrap_or("Error");
+    let body = if htmx {
+        render(&ErrorFragmentTemplate {
+            status: code,
+            reason,
+        })
+    } else if status == StatusCode::NOT_FOUND {
+        render(&NotFoundTemplate { path })
+    } else {
+        render(&ErrorTemplate {
+            status: code,
+            reason,
+        })
+    };
+    match body {
+        Ok(body) => (
+            status,
+            [
+                (header::CONTENT_TYPE, "text/html"),
+                (header::VARY, HTMX_VARY),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(_) => status.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/missing",
+                get(|| async { ErrorPage(StatusCode::NOT_FOUND) }),
+            )
+            .route(
+                "/broken",
+                get(|| async { ErrorPage(StatusCode::INTERNAL_SERVER_ERROR) }),
+            )
+            .route(
+                "/own-body",
+                get(|| async { (StatusCode::NOT_FOUND, "no such post") }),
+            )
+            .route("/bare", get(|| async { StatusCode::BAD_REQUEST }))
+            .layer(middleware::from_fn(error_pages))
+    }
+
+    async fn get_page(path: &str, htmx: bool) -> (StatusCode, HeaderMap, String) {
+        let mut request = Request::builder().uri(path);
+        if htmx {
+            request = request.header("hx-request", "true");
+        }
+        let response = app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (
+            parts.status,
+            parts.headers,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn error_statuses_get_a_rendered_page() {
+        let (status, headers, body) = get_page("/missing", false).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(headers[header::CONTENT_TYPE], "text/html");
+        assert!(body.starts_with("<!doctype html>"), "{body}");
+        assert!(body.contains("<code>/missing</code>"), "{body}");
+
+        let (status, _, body) = get_page("/broken", false).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body.contains("500 Internal Server Error"), "{body}");
+        assert!(body.contains("went wrong on our side"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn htmx_swaps_get_an_error_fragment() {
+        let (status, _, body) = get_page("/broken", true).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body,
+            "<p class=\"error\" role=\"alert\"><small>500 Internal Server Error</small></p>"
+        );
+    }
+
+    #[tokio::test]
+    async fn rendered_errors_vary_on_the_htmx_headers() {
+        for htmx in [false, true] {
+            let (_, headers, _) = get_page("/missing", htmx).await;
+            assert_eq!(headers[header::VARY], HTMX_VARY);
+        }
+    }
+
+    #[tokio::test]
+    async fn other_responses_pass_through_unchanged() {
+        let (status, _, body) = get_page("/own-body", false).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "no such post");
+
+        let (status, headers, body) = get_page("/bare", false).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(!headers.contains_key(header::CONTENT_TYPE));
+        assert_eq!(body, "");
+    }
+
+    #[test]
+    fn only_plain_htmx_requests_want_fragments() {
+        let headers = |names: &[&'static str]| {
+            let mut headers = HeaderMap::new();
+            for &name in names {
+                headers.insert(name, "true".parse().unwrap());
+            }
+            headers
+        };
+        let fragment = |names| Htmx::from_headers(&headers(names)).wants_fragment();
+        assert!(fragment(&["hx-request"]));
+        assert!(!fragment(&[]));
+        assert!(!fragment(&["hx-request", "hx-boosted"]));
+        assert!(!fragment(&["hx-request", "hx-history-restore-request"]));
+    }
+}
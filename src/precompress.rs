@@ -0,0 +1,281 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+// Already compressed formats such as woff2 gain nothing.
+const COMPRESSIBLE: [&str; 7] = ["wasm", "js", "css", "svg", "json", "txt", "ico"];
+// Below this a compressed sibling is not worth the extra file.
+const MIN_BYTES: u64 = 1024;
+
+/// The encodings ServeDir negotiates, as `<file>.<extension>` siblings.
+#[derive(Clone, Copy)]
+pub enum Encoding {
+    Brotli,
+    Zstd,
+    Gzip,
+}
+
+impl Encoding {
+    /// In order of preference.
+    pub const ALL: [Encoding; 3] = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+
+    fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zst",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    /// The `Content-Encoding` token.
+    #[cfg(feature = "embed")]
+    pub fn token(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// Slowest, smallest settings, since this runs once per file.
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+                writer.write_all(data)?;
+                Ok(writer.into_inner())
+            }
+            Encoding::Zstd => zstd::encode_all(data, 19),
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub written: usize,
+    pub fresh: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "wrote {} file(s), {} already up to date",
+            self.written, self.fresh
+        )
+    }
+}
+
+/// Writes `.br`, `.zst` and `.gz` siblings for the compressible files under
+/// `dir`, keeping siblings that are newer than their source.
+pub fn precompress(dir: &Path) -> io::Result<Summary> {
+    let mut summary = Summary::default();
+    for path in files(dir)? {
+        let metadata = fs::metadata(&path)?;
+        if !path
+            .to_str()
+            .is_some_and(|name| compressible(name, metadata.len()))
+        {
+            continue;
+        }
+        let modified = metadata.modified()?;
+        let mut data = None;
+        for encoding in Encoding::ALL {
+            let sibling = sibling(&path, encoding.extension());
+            let fresh = fs::metadata(&sibling)
+                .and_then(|sibling| sibling.modified())
+                .is_ok_and(|sibling| sibling >= modified);
+            if fresh {
+                summary.fresh += 1;
+                continue;
+            }
+            let data = match &data {
+                Some(data) => data,
+                None => data.insert(fs::read(&path)?),
+            };
+            // Written aside and renamed so ServeDir never sees half a file.
+            let partial = sibling.with_extension(format!("{}.partial", encoding.extension()));
+            fs::write(&partial, encoding.compress(data)?)?;
+            fs::rename(&partial, &sibling)?;
+            summary.written += 1;
+        }
+    }
+    Ok(summary)
+}
+
+pub fn compressible(name: &str, len: u64) -> bool {
+    let known = name
+        .rsplit_once('.')
+        .is_some_and(|(_, extension)| COMPRESSIBLE.contains(&extension));
+    known && len >= MIN_BYTES
+}
+
+/// Whether `name` is a compressed sibling rather than an asset of its own.
+pub fn is_encoded(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        Encoding::ALL
+            .iter()
+            .any(|encoding| encoding.extension() == extension)
+    })
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+fn files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            found.extend(files(&path)?);
+        } else if !path.to_str().is_some_and(is_encoded) {
+            found.push(path);
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("precompress-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Compressible text comfortably over `MIN_BYTES`.
+    fn text() -> Vec<u8> {
+        "function step() { return 1; }\n".repeat(100).into_bytes()
+    }
+
+    fn decompress(encoding: Encoding, data: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        match encoding {
+            Encoding::Brotli => {
+                brotli::Decompressor::new(data, 4096)
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+            }
+            Encoding::Zstd => decoded = zstd::decode_all(data).unwrap(),
+            Encoding::Gzip => {
+                flate2::read::GzDecoder::new(data)
+                    .read_to_end(&mut decoded)
+                    .unwrap();
+            }
+        }
+        decoded
+    }
+
+    #[test]
+    fn every_encoding_round_trips() {
+        let text = text();
+        for encoding in Encoding::ALL {
+            let compressed = encoding.compress(&text).unwrap();
+            assert!(compressed.len() < text.len());
+            assert_eq!(decompress(encoding, &compressed), text);
+        }
+    }
+
+    #[test]
+    fn siblings_are_written_next_to_each_compressible_file() {
+        let dir = temp_dir("siblings");
+        fs::create_dir(dir.join("js")).unwrap();
+        fs::write(dir.join("js/app.js"), text()).unwrap();
+
+        let summary = precompress(&dir).unwrap();
+        assert_eq!((summary.written, summary.fresh), (3, 0));
+        for encoding in Encoding::ALL {
+            let sibling = dir.join(format!("js/app.js.{}", encoding.extension()));
+            assert_eq!(decompress(encoding, &fs::read(sibling).unwrap()), text());
+        }
+        let partials = fs::read_dir(dir.join("js"))
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".partial")
+            })
+            .count();
+        assert_eq!(partials, 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fresh_siblings_are_kept_until_the_source_changes() {
+        let dir = temp_dir("fresh");
+        let source = dir.join("styles.css");
+        fs::write(&source, text()).unwrap();
+        precompress(&dir).unwrap();
+
+        let summary = precompress(&dir).unwrap();
+        assert_eq!((summary.written, summary.fresh), (0, 3));
+
+        let later = SystemTime::now() + Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let summary = precompress(&dir).unwrap();
+        assert_eq!((summary.written, summary.fresh), (3, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn small_encoded_and_incompressible_files_are_skipped() {
+        let dir = temp_dir("skipped");
+        fs::write(dir.join("small.js"), "let x = 1;").unwrap();
+        fs::write(dir.join("font.woff2"), text()).unwrap();
+        fs::write(dir.join("app.js.gz"), text()).unwrap();
+
+        let summary = precompress(&dir).unwrap();
+        assert_eq!((summary.written, summary.fresh), (0, 0));
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["app.js.gz", "font.woff2", "small.js"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compressible_needs_a_known_extension_and_size() {
+        assert!(compressible("pong_bg.wasm", MIN_BYTES));
+        assert!(compressible("assets/favicon.ico", MIN_BYTES));
+        assert!(!compressible("pong_bg.wasm", MIN_BYTES - 1));
+        assert!(!compressible("HackNerdFont-Regular.woff2", MIN_BYTES));
+        assert!(!compressible("Makefile", MIN_BYTES));
+    }
+
+    #[test]
+    fn encoded_siblings_are_recognised_by_extension() {
+        assert!(is_encoded("htmx.min.js.br"));
+        assert!(is_encoded("htmx.min.js.zst"));
+        assert!(is_encoded("htmx.min.js.gz"));
+        assert!(!is_encoded("htmx.min.js"));
+        assert!(!is_encoded("gz"));
+    }
+}
@@ -0,0 +1,450 @@
+use std::{
+    fmt,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use axum::body::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::export;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A résumé in the JSON Resume schema, see <https://jsonresume.org/schema>.
+/// Fields the schema has no place for are kept under `x-` prefixed keys.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Resume {
+    pub basics: Basics,
+    #[serde(default)]
+    pub skills: Vec<Skill>,
+    #[serde(default)]
+    pub work: Vec<Work>,
+    #[serde(default)]
+    pub education: Vec<Education>,
+    #[serde(default)]
+    pub publications: Vec<Publication>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Basics {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub summary: String,
+    pub location: Location,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Location {
+    pub city: String,
+    pub postal_code: String,
+    pub country_code: String,
+    #[serde(rename = "x-country")]
+    pub country: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Profile {
+    pub network: String,
+    pub username: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Skill {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Work {
+    pub name: String,
+    pub location: String,
+    pub position: String,
+    pub start_date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+    #[serde(default)]
+    pub highlights: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Education {
+    pub institution: String,
+    pub area: String,
+    #[serde(rename = "x-location")]
+    pub location: String,
+    #[serde(rename = "x-status", default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    pub start_date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+    #[serde(rename = "x-summary", default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Publication {
+    pub name: String,
+    pub publisher: String,
+    pub release_date: String,
+    pub summary: String,
+}
+
+#[derive(Debug)]
+pub enum ResumeError {
+    Read(std::io::Error),
+    Parse(toml::de::Error),
+    Date(String),
+}
+
+impl fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResumeError::Read(err) => write!(f, "{err}"),
+            ResumeError::Parse(err) => write!(f, "{err}"),
+            ResumeError::Date(date) => write!(
+                f,
+                "invalid date `{date}`, expected YYYY, YYYY-MM or YYYY-MM-DD"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResumeError {}
+
+impl Resume {
+    pub fn load(path: &Path) -> Result<Self, ResumeError> {
+        let text = match std::fs::read_to_string(path) {
+            #[cfg(feature = "embed")]
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("{} not found, using the embedded résumé", path.display());
+                crate::embed::resume()
+            }
+            text => text,
+        }
+        .map_err(ResumeError::Read)?;
+        let resume: Resume = toml::from_str(&text).map_err(ResumeError::Parse)?;
+
+        let work = resume
+            .work
+            .iter()
+            .flat_map(|work| [Some(&work.start_date), work.end_date.as_ref()]);
+        let education = resume
+            .education
+            .iter()
+            .flat_map(|education| [Some(&education.start_date), education.end_date.as_ref()]);
+        let publications = resume
+            .publications
+            .iter()
+            .map(|publication| Some(&publication.release_date));
+        if let Some(date) = work
+            .chain(education)
+            .chain(publications)
+            .flatten()
+            .find(|date| parse_date(date).is_none())
+        {
+            return Err(ResumeError::Date(date.clone()));
+        }
+        Ok(resume)
+    }
+
+    /// The handle shared by the profiles, e.g. `mskasal`.
+    pub fn handle(&self) -> &str {
+        self.basics
+            .profiles
+            .first()
+            .map_or("", |profile| profile.username.as_str())
+    }
+}
+
+/// A loaded résumé with its downloads, rendered on first use.
+pub struct Loaded {
+    resume: Resume,
+    text: OnceLock<String>,
+    pdf: OnceLock<Bytes>,
+}
+
+impl Loaded {
+    pub fn text(&self) -> &str {
+        self.text.get_or_init(|| export::text(&self.resume))
+    }
+
+    pub fn pdf(&self) -> Result<Bytes, printpdf::Error> {
+        if let Some(pdf) = self.pdf.get() {
+            return Ok(pdf.clone());
+        }
+        let pdf = Bytes::from(export::pdf(&self.resume)?);
+        Ok(self.pdf.get_or_init(|| pdf).clone())
+    }
+}
+
+impl Deref for Loaded {
+    type Target = Resume;
+
+    fn deref(&self) -> &Resume {
+        &self.resume
+    }
+}
+
+pub struct ResumeFile {
+    path: PathBuf,
+    current: RwLock<Arc<Loaded>>,
+}
+
+impl ResumeFile {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, ResumeError> {
+        let path = path.into();
+        let loaded = Self::read(&path)?;
+        Ok(ResumeFile {
+            path,
+            current: RwLock::new(Arc::new(loaded)),
+        })
+    }
+
+    /// Re-reads the résumé, dropping cached downloads. Keeps the current one on error.
+    pub fn reload(&self) -> Result<(), ResumeError> {
+        let loaded = Self::read(&self.path)?;
+        *self.current.write().unwrap() = Arc::new(loaded);
+        Ok(())
+    }
+
+    pub fn current(&self) -> Arc<Loaded> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(path: &Path) -> Result<Loaded, ResumeError> {
+        Ok(Loaded {
+            resume: Resume::load(path)?,
+            text: OnceLock::new(),
+            pdf: OnceLock::new(),
+        })
+    }
+}
+
+impl Work {
+    pub fn period(&self) -> String {
+        period(&self.start_date, self.end_date.as_deref())
+    }
+}
+
+impl Education {
+    pub fn period(&self) -> String {
+        period(&self.start_date, self.end_date.as_deref())
+    }
+}
+
+impl Publication {
+    /// `Jan 19, 2015`
+    pub fn filed(&self) -> String {
+        match parse_date(&self.release_date) {
+            Some((year, Some(month), Some(day))) => {
+                format!("{} {day}, {year}", &MONTHS[month - 1][..3])
+            }
+            _ => self.release_date.clone(),
+        }
+    }
+}
+
+fn period(start: &str, end: Option<&str>) -> String {
+    format!(
+        "{} - {}",
+        month_year(start),
+        end.map_or_else(|| String::from("Present"), month_year)
+    )
+}
+
+/// `2020-08` becomes `August 2020`, a bare year is kept as is.
+fn month_year(date: &str) -> String {
+    match parse_date(date) {
+        Some((year, Some(month), _)) => format!("{} {year}", MONTHS[month - 1]),
+        _ => date.to_string(),
+    }
+}
+
+fn parse_date(date: &str) -> Option<(u16, Option<usize>, Option<u8>)> {
+    let mut parts = date.split('-');
+    let year = parts.next()?.parse().ok()?;
+    let month = match parts.next() {
+        Some(month) => Some(
+            month
+                .parse()
+                .ok()
+                .filter(|month| (1..=12).contains(month))?,
+        ),
+        None => None,
+    };
+    let day = match parts.next() {
+        Some(day) => Some(day.parse().ok().filter(|day| (1..=31).contains(day))?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    const BUNDLED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/content/resume.toml");
+
+    // Keys the JSON Resume schema defines for the sections served here.
+    const SCHEMA: [(&str, &[&str]); 8] = [
+        (
+            "",
+            &["basics", "work", "education", "skills", "publications"],
+        ),
+        (
+            "basics",
+            &[
+                "name", "label", "image", "email", "phone", "url", "summary", "location",
+                "profiles",
+            ],
+        ),
+        (
+            "location",
+            &["address", "postalCode", "city", "countryCode", "region"],
+        ),
+        ("profiles", &["network", "username", "url"]),
+        ("skills", &["name", "level", "keywords"]),
+        (
+            "work",
+            &[
+                "name",
+                "location",
+                "description",
+                "position",
+                "url",
+                "startDate",
+                "endDate",
+                "summary",
+                "highlights",
+            ],
+        ),
+        (
+            "education",
+            &[
+                "institution",
+                "url",
+                "area",
+                "studyType",
+                "startDate",
+                "endDate",
+                "score",
+                "courses",
+            ],
+        ),
+        (
+            "publications",
+            &["name", "publisher", "releaseDate", "url", "summary"],
+        ),
+    ];
+
+    /// Collects the keys of every object under `value` that neither the
+    /// schema section named `section` nor an `x-` prefix allows.
+    fn stray_keys(section: &str, value: &Value, stray: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                let allowed = SCHEMA
+                    .iter()
+                    .find(|(name, _)| *name == section)
+                    .map_or(&[][..], |(_, keys)| keys);
+                for (key, value) in object {
+                    if key.starts_with("x-") {
+                        continue;
+                    }
+                    if !allowed.contains(&key.as_str()) {
+                        stray.push(format!("{section}.{key}"));
+                    }
+                    stray_keys(key, value, stray);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    stray_keys(section, item, stray);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn the_bundled_resume_loads() {
+        let resume = Resume::load(Path::new(BUNDLED)).unwrap();
+        assert_eq!(resume.basics.name, "Mustafa Samed Kasal");
+        assert_eq!(resume.handle(), "mskasal");
+        assert!(!resume.work.is_empty() && !resume.education.is_empty());
+        assert_eq!(resume.basics.location.country, "Germany");
+        assert_eq!(resume.education[0].status.as_deref(), Some("Dropped"));
+    }
+
+    #[test]
+    fn the_json_only_has_schema_or_extension_keys() {
+        let resume = Resume::load(Path::new(BUNDLED)).unwrap();
+        let json = serde_json::to_value(&resume).unwrap();
+        let mut stray = Vec::new();
+        stray_keys("", &json, &mut stray);
+        assert!(stray.is_empty(), "not in the JSON Resume schema: {stray:?}");
+        assert_eq!(json["basics"]["location"]["x-country"], "Germany");
+        assert_eq!(json["education"][0]["x-status"], "Dropped");
+    }
+
+    #[test]
+    fn unprefixed_extension_keys_are_rejected() {
+        let text = std::fs::read_to_string(BUNDLED)
+            .unwrap()
+            .replace("x-status", "status");
+        let err = toml::from_str::<Resume>(&text).unwrap_err();
+        assert!(err.message().contains("status"), "{err}");
+    }
+
+    #[test]
+    fn dates_read_as_month_and_year() {
+        assert_eq!(
+            period("2020-08", Some("2024-04")),
+            "August 2020 - April 2024"
+        );
+        assert_eq!(period("2007", None), "2007 - Present");
+        assert_eq!(parse_date("2024-13"), None);
+        assert_eq!(parse_date("2024-02-30-1"), None);
+    }
+}
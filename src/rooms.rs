@@ -0,0 +1,711 @@
+use std::{collections::HashMap, fmt, mem, time::Duration};
+
+use pong_core::{Game, Inputs, Settings, STEPS_PER_SECOND};
+use pong_protocol::{Direction, Phase, ServerMessage, Side, Target, VERSION};
+use rand::Rng;
+use tokio::{
+    sync::{broadcast, mpsc, oneshot, Mutex},
+    time::{self, Instant, MissedTickBehavior},
+};
+
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / STEPS_PER_SECOND as u64);
+const ROOM_TTL: Duration = Duration::from_secs(30);
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub enum LobbyError {
+    UnknownRoom,
+    RoomFull,
+    InvalidToken,
+    InvalidMessage(String),
+    BinaryMessage,
+    UnsupportedVersion(u32),
+    NotInRoom,
+    AlreadyInRoom,
+    OpponentLeft,
+    RoomClosed,
+}
+
+impl fmt::Display for LobbyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LobbyError::UnknownRoom => write!(f, "unknown room"),
+            LobbyError::RoomFull => write!(f, "room is full"),
+            LobbyError::InvalidToken => write!(f, "invalid rejoin token"),
+            LobbyError::InvalidMessage(err) => write!(f, "invalid message: {err}"),
+            LobbyError::BinaryMessage => write!(f, "binary messages are not supported"),
+            LobbyError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported protocol version {version}, the server speaks {VERSION}"
+            ),
+            LobbyError::NotInRoom => write!(f, "join a room first"),
+            LobbyError::AlreadyInRoom => write!(f, "already in a room"),
+            LobbyError::OpponentLeft => write!(f, "your opponent left, finding a new one"),
+            LobbyError::RoomClosed => write!(f, "your opponent left, join another room"),
+        }
+    }
+}
+
+impl std::error::Error for LobbyError {}
+
+enum RoomCommand {
+    Join {
+        token: Option<String>,
+        reply: oneshot::Sender<Result<Seat, LobbyError>>,
+    },
+    Input {
+        side: Side,
+        session: u64,
+        direction: Direction,
+    },
+    Leave {
+        side: Side,
+        session: u64,
+    },
+}
+
+struct Seat {
+    side: Side,
+    token: String,
+    session: u64,
+    full: bool,
+    events: broadcast::Receiver<ServerMessage>,
+}
+
+#[derive(Clone)]
+struct RoomHandle {
+    commands: mpsc::Sender<RoomCommand>,
+}
+
+impl RoomHandle {
+    fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+
+    async fn join(
+        &self,
+        code: &str,
+        token: Option<String>,
+    ) -> Result<(Membership, bool), LobbyError> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(RoomCommand::Join { token, reply })
+            .await
+            .map_err(|_| LobbyError::UnknownRoom)?;
+        let seat = response.await.map_err(|_| LobbyError::UnknownRoom)??;
+        let membership = Membership {
+            room: code.to_string(),
+            side: seat.side,
+            token: seat.token,
+            session: seat.session,
+            queued: false,
+            commands: self.commands.clone(),
+            events: seat.events,
+        };
+        Ok((membership, seat.full))
+    }
+}
+
+pub struct Membership {
+    pub room: String,
+    pub side: Side,
+    pub token: String,
+    /// Seated by matchmaking rather than a room code, so fine to requeue.
+    pub queued: bool,
+    session: u64,
+    commands: mpsc::Sender<RoomCommand>,
+    pub events: broadcast::Receiver<ServerMessage>,
+}
+
+impl Membership {
+    pub fn welcome(&self) -> ServerMessage {
+        ServerMessage::Welcome {
+            version: VERSION,
+            room: self.room.clone(),
+            side: self.side,
+            token: self.token.clone(),
+        }
+    }
+
+    pub async fn input(&self, direction: Direction) {
+        let _ = self
+            .commands
+            .send(RoomCommand::Input {
+                side: self.side,
+                session: self.session,
+                direction,
+            })
+            .await;
+    }
+
+    pub async fn leave(self) {
+        let _ = self
+            .commands
+            .send(RoomCommand::Leave {
+                side: self.side,
+                session: self.session,
+            })
+            .await;
+    }
+}
+
+#[derive(Default)]
+struct Rooms {
+    by_code: HashMap<String, RoomHandle>,
+    queue: Option<String>,
+}
+
+impl Rooms {
+    fn prune(&mut self) {
+        self.by_code.retain(|_, room| !room.is_closed());
+        if let Some(code) = &self.queue {
+            if !self.by_code.contains_key(code) {
+                self.queue = None;
+            }
+        }
+    }
+
+    fn open(&mut self, settings: Settings) -> (String, RoomHandle) {
+        let code = loop {
+            let code = random_code();
+            if !self.by_code.contains_key(&code) {
+                break code;
+            }
+        };
+        let (commands, receiver) = mpsc::channel(64);
+        let (events, _) = broadcast::channel(32);
+        let handle = RoomHandle { commands };
+        tokio::spawn(Room::new(code.clone(), settings, events).run(receiver));
+        self.by_code.insert(code.clone(), handle.clone());
+        (code, handle)
+    }
+}
+
+pub struct Lobby {
+    rooms: Mutex<Rooms>,
+    settings: Settings,
+}
+
+impl Lobby {
+    pub fn new(settings: Settings) -> Self {
+        Lobby {
+            rooms: Mutex::default(),
+            settings,
+        }
+    }
+
+    pub async fn enter(&self, target: Target) -> Result<Membership, LobbyError> {
+        match target {
+            Target::Queue => self.queue().await,
+            Target::Create => self.create().await,
+            Target::Room(code) => self.join(&code.to_uppercase(), None).await,
+            Target::Rejoin { room, token } => self.join(&room.to_uppercase(), Some(token)).await,
+        }
+    }
+
+    /// Seats the player in the room waiting for an opponent, or opens one.
+    /// Joins wait on the room, so they happen outside the lock.
+    pub async fn queue(&self) -> Result<Membership, LobbyError> {
+        loop {
+            let (code, room) = {
+                let mut rooms = self.rooms.lock().await;
+                rooms.prune();
+                match rooms.queue.clone() {
+                    Some(code) => {
+                        let room = rooms.by_code[&code].clone();
+                        (code, room)
+                    }
+                    None => {
+                        let (code, room) = rooms.open(self.settings);
+                        rooms.queue = Some(code.clone());
+                        (code, room)
+                    }
+                }
+            };
+            match room.join(&code, None).await {
+                Ok((mut membership, full)) => {
+                    if full {
+                        self.unqueue(&code).await;
+                    }
+                    membership.queued = true;
+                    return Ok(membership);
+                }
+                // Filled or closed since, try the next one.
+                Err(LobbyError::RoomFull | LobbyError::UnknownRoom) => self.unqueue(&code).await,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    async fn unqueue(&self, code: &str) {
+        let mut rooms = self.rooms.lock().await;
+        if rooms.queue.as_deref() == Some(code) {
+            rooms.queue = None;
+        }
+    }
+
+    pub async fn create(&self) -> Result<Membership, LobbyError> {
+        let (code, room) = {
+            let mut rooms = self.rooms.lock().await;
+            rooms.prune();
+            rooms.open(self.settings)
+        };
+        room.join(&code, None)
+            .await
+            .map(|(membership, _)| membership)
+    }
+
+    pub async fn join(&self, code: &str, token: Option<String>) -> Result<Membership, LobbyError> {
+        let room = {
+            let mut rooms = self.rooms.lock().await;
+            rooms.prune();
+            if rooms.queue.as_deref() == Some(code) {
+                rooms.queue = None;
+            }
+            rooms
+                .by_code
+                .get(code)
+                .cloned()
+                .ok_or(LobbyError::UnknownRoom)?
+        };
+        room.join(code, token)
+            .await
+            .map(|(membership, _)| membership)
+    }
+
+    pub async fn rooms(&self) -> usize {
+        let mut rooms = self.rooms.lock().await;
+        rooms.prune();
+        rooms.by_code.len()
+    }
+}
+
+#[derive(Default)]
+struct SeatState {
+    token: Option<String>,
+    session: u64,
+    connected: bool,
+}
+
+struct Room {
+    code: String,
+    game: Game,
+    /// Paddle moves received since the last tick.
+    inputs: Inputs,
+    scores: (u32, u32),
+    phase: Phase,
+    seats: [SeatState; 2],
+    started: bool,
+    sessions: u64,
+    events: broadcast::Sender<ServerMessage>,
+}
+
+impl Room {
+    fn new(code: String, settings: Settings, events: broadcast::Sender<ServerMessage>) -> Self {
+        let game = Game::with_settings(settings);
+        Self {
+            code,
+            scores: game.scores(),
+            phase: game.phase(),
+            game,
+            inputs: Inputs::default(),
+            seats: Default::default(),
+            started: false,
+            sessions: 0,
+            events,
+        }
+    }
+
+    async fn run(mut self, mut commands: mpsc::Receiver<RoomCommand>) {
+        let mut ticker = time::interval(TICK);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut last_seen = Instant::now();
+        tracing::debug!("room {} opened", self.code);
+
+        loop {
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => self.handle(command),
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    // Rooms live on while full or waiting for an opponent. An
+                    // empty room, or a match a player dropped out of, closes
+                    // once nobody has come back for `ROOM_TTL`, which the
+                    // remaining player sees as the events closing.
+                    let connected = self.seats.iter().filter(|seat| seat.connected).count();
+                    if connected == self.seats.len() || (connected > 0 && !self.started) {
+                        last_seen = Instant::now();
+                    } else if last_seen.elapsed() > ROOM_TTL {
+                        break;
+                    }
+                    let inputs = mem::take(&mut self.inputs);
+                    if self.seats.iter().all(|seat| seat.connected) {
+                        if self.game.phase() == Phase::Lobby {
+                            self.game.start();
+                        }
+                        self.game.step(inputs);
+                        self.broadcast(ServerMessage::State(self.game.snapshot()));
+                        if self.game.scores() != self.scores {
+                            self.scores = self.game.scores();
+                            self.broadcast_score();
+                        }
+                        if self.game.phase() != self.phase {
+                            self.phase = self.game.phase();
+                            self.broadcast(ServerMessage::Phase(self.phase));
+                        }
+                    }
+                }
+            }
+        }
+        tracing::debug!("room {} closed", self.code);
+    }
+
+    fn handle(&mut self, command: RoomCommand) {
+        match command {
+            RoomCommand::Join { token, reply } => {
+                let seat = self.seat(token);
+                if seat.is_ok() {
+                    self.broadcast_players();
+                    self.broadcast(ServerMessage::State(self.game.snapshot()));
+                    self.broadcast_score();
+                    self.broadcast(ServerMessage::Phase(self.phase));
+                }
+                let _ = reply.send(seat);
+            }
+            RoomCommand::Input {
+                side,
+                session,
+                direction,
+            } => {
+                // A rejoin takes the seat over from the session before it.
+                if self.started && self.seats[index(side)].session == session {
+                    self.inputs.press(side, direction);
+                }
+            }
+            RoomCommand::Leave { side, session } => {
+                let seat = &mut self.seats[index(side)];
+                if seat.session != session {
+                    return;
+                }
+                seat.connected = false;
+                if !self.started {
+                    seat.token = None;
+                }
+                self.broadcast_players();
+            }
+        }
+    }
+
+    fn seat(&mut self, token: Option<String>) -> Result<Seat, LobbyError> {
+        let side = match &token {
+            Some(token) => [Side::Left, Side::Right]
+                .into_iter()
+                .find(|side| self.seats[index(*side)].token.as_ref() == Some(token))
+                .ok_or(LobbyError::InvalidToken)?,
+            None => [Side::Left, Side::Right]
+                .into_iter()
+                .find(|side| self.seats[index(*side)].token.is_none())
+                .ok_or(LobbyError::RoomFull)?,
+        };
+
+        self.sessions += 1;
+        let seat = &mut self.seats[index(side)];
+        let token = seat.token.get_or_insert_with(random_token).clone();
+        seat.session = self.sessions;
+        seat.connected = true;
+        if self.seats.iter().all(|seat| seat.token.is_some()) {
+            self.started = true;
+        }
+
+        Ok(Seat {
+            side,
+            token,
+            session: self.sessions,
+            full: self.seats.iter().all(|seat| seat.token.is_some()),
+            events: self.events.subscribe(),
+        })
+    }
+
+    fn broadcast_players(&self) {
+        self.broadcast(ServerMessage::Players {
+            left: self.seats[0].connected,
+            right: self.seats[1].connected,
+        });
+    }
+
+    fn broadcast_score(&self) {
+        self.broadcast(ServerMessage::Score {
+            left: self.scores.0,
+            right: self.scores.1,
+        });
+    }
+
+    fn broadcast(&self, message: ServerMessage) {
+        let _ = self.events.send(message);
+    }
+}
+
+fn index(side: Side) -> usize {
+    match side {
+        Side::Left => 0,
+        Side::Right => 1,
+    }
+}
+
+fn random_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LENGTH)
+        .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+fn random_token() -> String {
+    format!("{:032x}", rand::thread_rng().gen::<u128>())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn lobby() -> Lobby {
+        Lobby::new(Settings::default())
+    }
+
+    /// Everything broadcast to `membership` so far.
+    fn drain(membership: &mut Membership) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = membership.events.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn two_queued_joins_share_a_room() {
+        let lobby = lobby();
+        let first = lobby.queue().await.unwrap();
+        let second = lobby.queue().await.unwrap();
+        assert_eq!(first.room, second.room);
+        assert_eq!(second.side, first.side.opponent());
+        assert_eq!(lobby.rooms().await, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_third_queued_join_opens_a_new_room() {
+        let lobby = lobby();
+        let first = lobby.queue().await.unwrap();
+        let _second = lobby.queue().await.unwrap();
+        let third = lobby.queue().await.unwrap();
+        assert_ne!(third.room, first.room);
+        assert_eq!(lobby.rooms().await, 2);
+
+        let fourth = lobby.queue().await.unwrap();
+        assert_eq!(fourth.room, third.room);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_busy_room_does_not_hold_up_the_lobby() {
+        let lobby = Arc::new(lobby());
+        // A queued room whose actor never answers.
+        let (commands, _stuck) = mpsc::channel(64);
+        {
+            let mut rooms = lobby.rooms.lock().await;
+            rooms
+                .by_code
+                .insert(String::from("BUSY2"), RoomHandle { commands });
+            rooms.queue = Some(String::from("BUSY2"));
+        }
+        let waiting = tokio::spawn({
+            let lobby = Arc::clone(&lobby);
+            async move { lobby.queue().await.map(|membership| membership.room) }
+        });
+        time::sleep(TICK).await;
+        assert!(!waiting.is_finished());
+
+        let created = time::timeout(Duration::from_secs(1), lobby.create()).await;
+        assert!(created.expect("create waited on the busy room").is_ok());
+        assert_eq!(lobby.rooms().await, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_matchmaking_seats_are_queued() {
+        let lobby = lobby();
+        assert!(lobby.queue().await.unwrap().queued);
+        let host = lobby.create().await.unwrap();
+        assert!(!host.queued);
+        let guest = lobby.enter(Target::Room(host.room.clone())).await.unwrap();
+        assert!(!guest.queued);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unknown_and_full_codes_are_rejected() {
+        let lobby = lobby();
+        assert_eq!(
+            lobby.enter(Target::Room(String::from("NOPE2"))).await.err(),
+            Some(LobbyError::UnknownRoom)
+        );
+
+        let host = lobby.create().await.unwrap();
+        let guest = lobby
+            .enter(Target::Room(host.room.to_lowercase()))
+            .await
+            .unwrap();
+        assert_eq!(guest.room, host.room);
+        assert_eq!(
+            lobby.enter(Target::Room(host.room.clone())).await.err(),
+            Some(LobbyError::RoomFull)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn private_rooms_are_not_queued_into() {
+        let lobby = lobby();
+        let host = lobby.create().await.unwrap();
+        let queued = lobby.queue().await.unwrap();
+        assert_ne!(queued.room, host.room);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_rejoin_with_the_token_gets_the_same_side() {
+        let lobby = lobby();
+        let first = lobby.queue().await.unwrap();
+        let _second = lobby.queue().await.unwrap();
+        let (room, side, token) = (first.room.clone(), first.side, first.token.clone());
+        first.leave().await;
+
+        assert_eq!(
+            lobby
+                .join(&room, Some(String::from("not the token")))
+                .await
+                .err(),
+            Some(LobbyError::InvalidToken)
+        );
+        let rejoined = lobby
+            .enter(Target::Rejoin {
+                room: room.clone(),
+                token: token.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(rejoined.room, room);
+        assert_eq!(rejoined.side, side);
+        assert_eq!(rejoined.token, token);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stale_sessions_leave_is_ignored() {
+        let lobby = lobby();
+        let stale = lobby.queue().await.unwrap();
+        let _other = lobby.queue().await.unwrap();
+        let mut current = lobby
+            .join(&stale.room, Some(stale.token.clone()))
+            .await
+            .unwrap();
+        time::sleep(TICK * 2).await;
+        drain(&mut current);
+
+        stale.leave().await;
+        time::sleep(TICK * 2).await;
+        let messages = drain(&mut current);
+        assert!(!messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::Players { .. })));
+        assert!(messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::State(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_stale_sessions_input_is_ignored() {
+        let lobby = lobby();
+        let stale = lobby.queue().await.unwrap();
+        let _other = lobby.queue().await.unwrap();
+        let mut current = lobby
+            .join(&stale.room, Some(stale.token.clone()))
+            .await
+            .unwrap();
+        let paddle = |membership: &mut Membership| {
+            let side = membership.side;
+            drain(membership)
+                .into_iter()
+                .rev()
+                .find_map(|message| match message {
+                    ServerMessage::State(snapshot) => Some(match side {
+                        Side::Left => snapshot.paddles.0,
+                        Side::Right => snapshot.paddles.1,
+                    }),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        time::sleep(TICK * 2).await;
+        let start = paddle(&mut current);
+
+        for _ in 0..10 {
+            stale.input(Direction::Down).await;
+            time::sleep(TICK).await;
+        }
+        assert_eq!(paddle(&mut current), start);
+
+        current.input(Direction::Down).await;
+        time::sleep(TICK * 2).await;
+        assert!(paddle(&mut current).y > start.y);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_empty_room_is_pruned_after_the_ttl() {
+        let lobby = lobby();
+        let host = lobby.create().await.unwrap();
+        host.leave().await;
+
+        time::sleep(ROOM_TTL - Duration::from_secs(1)).await;
+        assert_eq!(lobby.rooms().await, 1);
+        time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(lobby.rooms().await, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_lone_player_waits_for_an_opponent_past_the_ttl() {
+        let lobby = lobby();
+        let _waiting = lobby.queue().await.unwrap();
+        time::sleep(ROOM_TTL * 2).await;
+        assert_eq!(lobby.rooms().await, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_abandoned_match_closes_the_remaining_players_events() {
+        let lobby = lobby();
+        let leaving = lobby.queue().await.unwrap();
+        let mut staying = lobby.queue().await.unwrap();
+        leaving.leave().await;
+
+        time::sleep(ROOM_TTL - Duration::from_secs(1)).await;
+        assert_eq!(lobby.rooms().await, 1);
+        time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(lobby.rooms().await, 0);
+
+        drain(&mut staying);
+        assert_eq!(
+            staying.events.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_player_rejoining_in_time_keeps_the_match() {
+        let lobby = lobby();
+        let leaving = lobby.queue().await.unwrap();
+        let _staying = lobby.queue().await.unwrap();
+        let (room, token) = (leaving.room.clone(), leaving.token.clone());
+        leaving.leave().await;
+
+        time::sleep(ROOM_TTL - Duration::from_secs(1)).await;
+        let _rejoined = lobby.join(&room, Some(token)).await.unwrap();
+        time::sleep(ROOM_TTL).await;
+        assert_eq!(lobby.rooms().await, 1);
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use rand::Rng;
+
+use crate::config::SecurityConfig;
+
+tokio::task_local! {
+    static NONCE: String;
+}
+
+/// The CSP nonce of the request being handled, for inline `<script>` tags.
+/// Empty when the policy is switched off.
+pub fn nonce() -> String {
+    NONCE.try_with(String::clone).unwrap_or_default()
+}
+
+/// Headers sent with every response, and the Content-Security-Policy built
+/// around each request's nonce.
+pub struct SecurityHeaders {
+    csp: bool,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl SecurityHeaders {
+    /// Expects the policy strings to have been validated with the config.
+    pub fn new(config: &SecurityConfig) -> Self {
+        let mut headers = Vec::new();
+        if config.hsts_max_age_secs > 0 {
+            let hsts = format!("max-age={}", config.hsts_max_age_secs);
+            headers.push((
+                header::STRICT_TRANSPORT_SECURITY,
+                HeaderValue::try_from(hsts).expect("digits are a valid header"),
+            ));
+        }
+        if config.nosniff {
+            headers.push((
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            ));
+        }
+        let policies = [
+            (header::REFERRER_POLICY, &config.referrer_policy),
+            (
+                HeaderName::from_static("permissions-policy"),
+                &config.permissions_policy,
+            ),
+        ];
+        for (name, value) in policies {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                if !value.is_empty() {
+                    headers.push((name, value));
+                }
+            }
+        }
+        SecurityHeaders {
+            csp: config.csp,
+            headers,
+        }
+    }
+}
+
+pub async fn headers(
+    State(security): State<Arc<SecurityHeaders>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = if security.csp {
+        let nonce = format!("{:032x}", rand::thread_rng().gen::<u128>());
+        let policy = policy(&nonce);
+        let mut response = NONCE.scope(nonce, next.run(request)).await;
+        response.headers_mut().insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::try_from(policy).expect("nonces are hex"),
+        );
+        response
+    } else {
+        next.run(request).await
+    };
+    for (name, value) in &security.headers {
+        response.headers_mut().insert(name, value.clone());
+    }
+    response
+}
+
+/// Opts a page into cross-origin isolation, which `SharedArrayBuffer` and wasm
+/// threads need. Everything it embeds must then be same-origin or send CORP.
+pub fn isolate(response: &mut Response) {
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("cross-origin-opener-policy"),
+        HeaderValue::from_static("same-origin"),
+    );
+    headers.insert(
+        HeaderName::from_static("cross-origin-embedder-policy"),
+        HeaderValue::from_static("require-corp"),
+    );
+}
+
+/// Scripts need the nonce, or to come from /assets. Styles stay inline-able
+/// for the templates' `<style>` blocks and htmx's indicator styles.
+fn policy(nonce: &str) -> String {
+    [
+        "default-src 'self'",
+        &format!("script-src 'self' 'nonce-{nonce}' 'wasm-unsafe-eval'"),
+        "style-src 'self' 'unsafe-inline'",
+        "img-src 'self' data:",
+        "object-src 'none'",
+        "base-uri 'self'",
+        "form-action 'self'",
+        "frame-ancestors 'none'",
+    ]
+    .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::StatusCode, middleware, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::page;
+
+    // The 404 page renders head.html, which carries the nonce.
+    fn app(config: SecurityConfig) -> Router {
+        Router::new()
+            .fallback(page::not_found)
+            .layer(middleware::from_fn(page::error_pages))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(SecurityHeaders::new(&config)),
+                headers,
+            ))
+    }
+
+    async fn get(app: &Router) -> (Response, String) {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/nope").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (
+            Response::from_parts(parts, Body::empty()),
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    fn csp_nonce(response: &Response) -> String {
+        let policy = response.headers()[header::CONTENT_SECURITY_POLICY]
+            .to_str()
+            .unwrap();
+        let start = policy.find("'nonce-").unwrap() + "'nonce-".len();
+        policy[start..start + 32].to_string()
+    }
+
+    #[tokio::test]
+    async fn the_csp_nonce_signs_the_rendered_scripts() {
+        let app = app(SecurityConfig::default());
+        let (response, body) = get(&app).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let nonce = csp_nonce(&response);
+        assert!(nonce.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert!(
+            body.contains(&format!("<script nonce=\"{nonce}\">")),
+            "{body}"
+        );
+
+        let (again, _) = get(&app).await;
+        assert_ne!(csp_nonce(&again), nonce);
+    }
+
+    #[test]
+    fn the_policy_only_allows_own_and_nonced_scripts() {
+        let policy = policy("abc");
+        let script_src = policy
+            .split("; ")
+            .find(|directive| directive.starts_with("script-src"))
+            .unwrap();
+        assert_eq!(
+            script_src,
+            "script-src 'self' 'nonce-abc' 'wasm-unsafe-eval'"
+        );
+        assert!(policy.contains("object-src 'none'"));
+        assert!(policy.contains("frame-ancestors 'none'"));
+    }
+
+    #[tokio::test]
+    async fn configured_headers_are_sent() {
+        let (response, _) = get(&app(SecurityConfig::default())).await;
+        let headers = response.headers();
+        assert_eq!(
+            headers[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=63072000"
+        );
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(
+            headers[header::REFERRER_POLICY],
+            "strict-origin-when-cross-origin"
+        );
+        assert!(headers.contains_key("permissions-policy"));
+    }
+
+    #[tokio::test]
+    async fn switched_off_headers_are_left_out() {
+        let (response, body) = get(&app(SecurityConfig {
+            csp: false,
+            hsts_max_age_secs: 0,
+            nosniff: false,
+            referrer_policy: String::new(),
+            permissions_policy: String::new(),
+        }))
+        .await;
+        for name in [
+            header::CONTENT_SECURITY_POLICY,
+            header::STRICT_TRANSPORT_SECURITY,
+            header::X_CONTENT_TYPE_OPTIONS,
+            header::REFERRER_POLICY,
+            HeaderName::from_static("permissions-policy"),
+        ] {
+            assert!(!response.headers().contains_key(&name), "{name} sent");
+        }
+        assert!(body.contains("<script nonce=\"\">"), "{body}");
+    }
+
+    #[test]
+    fn there_is_no_nonce_outside_a_request() {
+        assert_eq!(nonce(), "");
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tokio::sync::{watch, Notify};
+
+pub struct Shutdown {
+    signal: watch::Sender<bool>,
+    websockets: AtomicUsize,
+    requests: AtomicUsize,
+    idle: Notify,
+    in_flight: OnceLock<(usize, usize)>,
+}
+
+pub struct Tracked<'a> {
+    counter: &'a AtomicUsize,
+    idle: &'a Notify,
+}
+
+impl Drop for Tracked<'_> {
+    fn drop(&mut self) {
+        if self.counter.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown {
+            signal: watch::channel(false).0,
+            websockets: AtomicUsize::new(0),
+            requests: AtomicUsize::new(0),
+            idle: Notify::new(),
+            in_flight: OnceLock::new(),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.in_flight
+            .get_or_init(|| (self.websockets(), self.requests()));
+        self.signal.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.signal.borrow()
+    }
+
+    /// Resolves once shutdown has been triggered, immediately if it already was.
+    pub fn wait(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut signal = self.signal.subscribe();
+        async move {
+            let _ = signal.wait_for(|&stopping| stopping).await;
+        }
+    }
+
+    /// Resolves once every tracked websocket and request has finished.
+    pub async fn drained(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.websockets() == 0 && self.requests() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    pub fn track_websocket(&self) -> Tracked<'_> {
+        self.track(&self.websockets)
+    }
+
+    fn track<'a>(&'a self, counter: &'a AtomicUsize) -> Tracked<'a> {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Tracked {
+            counter,
+            idle: &self.idle,
+        }
+    }
+
+    pub fn websockets(&self) -> usize {
+        self.websockets.load(Ordering::Relaxed)
+    }
+
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Websockets and requests that were open when shutdown was triggered.
+    pub fn in_flight(&self) -> (usize, usize) {
+        self.in_flight.get().copied().unwrap_or_default()
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub async fn track_requests(
+    State(shutdown): State<Arc<Shutdown>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _tracked = shutdown.track(&shutdown.requests);
+    next.run(request).await
+}
+
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("received SIGTERM, shutting down"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tokio::time;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Spawns a task waiting for `shutdown` to drain.
+    fn draining(shutdown: &Arc<Shutdown>) -> tokio::task::JoinHandle<()> {
+        let shutdown = Arc::clone(shutdown);
+        tokio::spawn(async move { shutdown.drained().await })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nothing_tracked_is_already_drained() {
+        let shutdown = Shutdown::new();
+        time::timeout(Duration::from_secs(1), shutdown.drained())
+            .await
+            .expect("drained waited with nothing tracked");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn draining_waits_for_the_last_tracked_connection() {
+        let shutdown = Arc::new(Shutdown::new());
+        let first = shutdown.track_websocket();
+        let second = shutdown.track_websocket();
+        let request = shutdown.track(&shutdown.requests);
+        let drained = draining(&shutdown);
+
+        time::sleep(Duration::from_secs(60)).await;
+        assert!(!drained.is_finished());
+        drop(first);
+        drop(request);
+        time::sleep(Duration::from_secs(60)).await;
+        assert!(!drained.is_finished());
+
+        drop(second);
+        time::timeout(Duration::from_secs(1), drained)
+            .await
+            .expect("drained after the last connection closed")
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn counters_follow_the_tracked_guards() {
+        let shutdown = Shutdown::new();
+        let first = shutdown.track_websocket();
+        let second = shutdown.track_websocket();
+        assert_eq!((shutdown.websockets(), shutdown.requests()), (2, 0));
+        drop(first);
+        assert_eq!(shutdown.websockets(), 1);
+        drop(second);
+        assert_eq!(shutdown.websockets(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_are_tracked_while_they_run() {
+        let shutdown = Arc::new(Shutdown::new());
+        let app = Router::new()
+            .route(
+                "/",
+                get({
+                    let shutdown = Arc::clone(&shutdown);
+                    move || async move { shutdown.requests().to_string() }
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::clone(&shutdown),
+                track_requests,
+            ));
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "1");
+        assert_eq!(shutdown.requests(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn triggering_wakes_waiters_and_counts_what_was_in_flight() {
+        let shutdown = Shutdown::new();
+        assert_eq!(shutdown.in_flight(), (0, 0));
+        let waiting = tokio::spawn(shutdown.wait());
+        let _websocket = shutdown.track_websocket();
+        let request = shutdown.track(&shutdown.requests);
+        time::sleep(Duration::from_secs(60)).await;
+        assert!(!waiting.is_finished());
+
+        shutdown.trigger();
+        assert!(shutdown.is_triggered());
+        waiting.await.unwrap();
+        time::timeout(Duration::from_secs(1), shutdown.wait())
+            .await
+            .expect("wait resolves once triggered");
+
+        // Only the first trigger counts.
+        drop(request);
+        shutdown.trigger();
+        assert_eq!(shutdown.in_flight(), (1, 1));
+    }
+}